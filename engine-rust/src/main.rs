@@ -1,8 +1,10 @@
-use std::collections::HashMap;
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::fs::{self, File};
-use std::io::{BufReader, BufWriter, Read, Write};
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::io::{BufWriter, Write};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::OnceLock;
 use std::thread;
 use std::time::{Instant, SystemTime, UNIX_EPOCH};
 
@@ -129,11 +131,95 @@ fn hand_sort_key(cards: &[u8; 5]) -> u64 {
         key = key * 14 + (12 - t) as u64;
     }
     for _ in tbs.len()..5 {
-        key = key * 14;
+        key *= 14;
     }
     key
 }
 
+/// Names of the `hand_sort_key` categories, indexed by category (best first).
+const CATEGORY_NAMES: [&str; 9] = [
+    "Straight Flush",
+    "Four of a Kind",
+    "Full House",
+    "Flush",
+    "Straight",
+    "Three of a Kind",
+    "Two Pair",
+    "One Pair",
+    "High Card",
+];
+
+/// One past the last eval-table rank of each category, recorded by
+/// `init_eval_table` from the sorted hand keys. Ranks are dense and ordered
+/// by `hand_sort_key`, whose leading digit is the category.
+static CATEGORY_RANK_ENDS: OnceLock<[u16; 9]> = OnceLock::new();
+
+fn category_rank_ends() -> &'static [u16; 9] {
+    CATEGORY_RANK_ENDS.get().expect("init_eval_table must run before category lookups")
+}
+
+fn rank_to_category(rank: u16) -> u8 {
+    category_rank_ends().iter().position(|&end| rank < end).unwrap_or(8) as u8
+}
+
+const RANK_NAMES: [&str; 13] = [
+    "Two", "Three", "Four", "Five", "Six", "Seven", "Eight",
+    "Nine", "Ten", "Jack", "Queen", "King", "Ace",
+];
+
+fn rank_plural(rank: u8) -> String {
+    match rank {
+        4 => "Sixes".into(),
+        r => format!("{}s", RANK_NAMES[r as usize]),
+    }
+}
+
+/// Human-readable name of a 5-card hand, e.g. "Kings full of Sevens",
+/// "Ace-high flush" or "Pair of Jacks".
+fn describe_hand(cards: &[u8; 5]) -> String {
+    let mut counts = [0u8; 13];
+    for &c in cards {
+        counts[(c % 13) as usize] += 1;
+    }
+    let with_count = |n: u8| (0..13u8).rev().filter(move |&r| counts[r as usize] == n);
+    let high = with_count(1).next().unwrap_or(0);
+    // A wheel plays the Ace low.
+    let wheel = [12, 0, 1, 2, 3].iter().all(|&r| counts[r] == 1);
+    let straight_high = if wheel { 3 } else { high };
+    match (hand_sort_key(cards) / 14u64.pow(5)) as u8 {
+        0 if straight_high == 12 => "Royal flush".into(),
+        0 => format!("{}-high straight flush", RANK_NAMES[straight_high as usize]),
+        1 => format!("Four {}", rank_plural(with_count(4).next().unwrap())),
+        2 => format!("{} full of {}",
+            rank_plural(with_count(3).next().unwrap()), rank_plural(with_count(2).next().unwrap())),
+        3 => format!("{}-high flush", RANK_NAMES[high as usize]),
+        4 => format!("{}-high straight", RANK_NAMES[straight_high as usize]),
+        5 => format!("Three {}", rank_plural(with_count(3).next().unwrap())),
+        6 => {
+            let mut pairs = with_count(2);
+            let (hi, lo) = (pairs.next().unwrap(), pairs.next().unwrap());
+            format!("{} and {}", rank_plural(hi), rank_plural(lo))
+        }
+        7 => format!("Pair of {}", rank_plural(with_count(2).next().unwrap())),
+        _ => format!("{} high", RANK_NAMES[high as usize]),
+    }
+}
+
+/// Best 5-card hand from exactly two hole cards and three board cards, as
+/// (eval-table rank, hole cards used, board cards used).
+fn best_hand(hand: &[u8; 5], board: &[u8; 5], table: &[u16]) -> (u16, [u8; 2], [u8; 3]) {
+    let mut best = (u16::MAX, [0u8; 2], [0u8; 3]);
+    let mut merged = [0u8; 5];
+    for h in &two_card_subsets(hand) {
+        for b in &three_card_subsets(board) {
+            merge5(h, b, &mut merged);
+            let rank = table[comb_index(&merged)];
+            if rank < best.0 { best = (rank, *h, *b); }
+        }
+    }
+    best
+}
+
 fn init_eval_table() -> Vec<u16> {
     let total = BINOM[52][5] as usize;
     let mut keys_with_idx: Vec<(u64, usize)> = Vec::with_capacity(total);
@@ -165,6 +251,13 @@ fn init_eval_table() -> Vec<u16> {
 
     let num_ranks = unique_keys.len();
     eprintln!("  Distinct hand ranks: {}", num_ranks);
+    let mut category_ends = [0u16; 9];
+    for (rank, &key) in unique_keys.iter().enumerate() {
+        category_ends[(key / 14u64.pow(5)) as usize] = rank as u16 + 1;
+    }
+    assert!(category_ends.windows(2).all(|w| w[0] < w[1]) && category_ends[8] as usize == num_ranks,
+        "eval table is missing a hand category: {:?}", category_ends);
+    CATEGORY_RANK_ENDS.get_or_init(|| category_ends);
 
     let mut table = vec![0u16; total];
     for &(key, idx) in &keys_with_idx {
@@ -173,48 +266,370 @@ fn init_eval_table() -> Vec<u16> {
     table
 }
 
+/// Suit relabelling that puts a list of card groups (hand, then optionally
+/// dead cards and board) into canonical form.
+///
+/// Each suit is keyed by one rank set per group, compared so that the set
+/// holding the lowest rank of their symmetric difference sorts first.
+/// Relabelling suits 0..4 in key order gives the same result as trying all
+/// 24 permutations and keeping the lexicographically smallest sorted groups:
+/// a suit whose ranks are a superset of another's must come first, because
+/// its extra card then stays in the lower suit block instead of sorting after
+/// every card of the other suit.
+fn canonical_suit_perm(groups: &[&[u8]]) -> [u8; 4] {
+    debug_assert!(groups.len() <= 4);
+    let mut keys = [[u16::MAX; 4]; 4];
+    for (g, cards) in groups.iter().enumerate() {
+        for &c in cards.iter() {
+            keys[(c / 13) as usize][g] &= !(1u16 << (12 - c % 13));
+        }
+    }
+    let mut order = [0u8, 1, 2, 3];
+    order.sort_by_key(|&su| keys[su as usize]);
+    let mut perm = [0u8; 4];
+    for (new_suit, &old_suit) in order.iter().enumerate() {
+        perm[old_suit as usize] = new_suit as u8;
+    }
+    perm
+}
+
+fn apply_suit_perm<const N: usize>(cards: &[u8; N], perm: &[u8; 4]) -> [u8; N] {
+    let mut mapped = [0u8; N];
+    for i in 0..N {
+        mapped[i] = perm[(cards[i] / 13) as usize] * 13 + cards[i] % 13;
+    }
+    mapped.sort_unstable();
+    mapped
+}
+
 fn canonicalize(cards: &[u8; 5]) -> [u8; 5] {
-    let mut best = [255u8; 5];
-    for p0 in 0..4u8 {
-        for p1 in 0..4u8 {
-            if p1 == p0 { continue; }
-            for p2 in 0..4u8 {
-                if p2 == p0 || p2 == p1 { continue; }
-                let p3 = 6 - p0 - p1 - p2;
-                let perm = [p0, p1, p2, p3];
-                let mut mapped = [0u8; 5];
-                for i in 0..5 {
-                    let rank = cards[i] % 13;
-                    let suit = cards[i] / 13;
-                    mapped[i] = perm[suit as usize] * 13 + rank;
+    apply_suit_perm(cards, &canonical_suit_perm(&[cards]))
+}
+
+/// All 24 relabellings of the four suits, as `apply_suit_perm` takes them.
+const SUIT_PERMS: [[u8; 4]; 24] = {
+    let mut perms = [[0u8; 4]; 24];
+    let mut n = 0;
+    let mut p0 = 0u8;
+    while p0 < 4 {
+        let mut p1 = 0u8;
+        while p1 < 4 {
+            let mut p2 = 0u8;
+            while p2 < 4 {
+                if p1 != p0 && p2 != p0 && p2 != p1 {
+                    perms[n] = [p0, p1, p2, 6 - p0 - p1 - p2];
+                    n += 1;
                 }
-                mapped.sort();
-                if mapped < best {
-                    best = mapped;
+                p2 += 1;
+            }
+            p1 += 1;
+        }
+        p0 += 1;
+    }
+    perms
+};
+
+/// Colex index of a sorted k-card subset (k <= 5) of the 52-card deck, in
+/// 0..C(52, k). `comb_index` is the k = 5 case.
+fn subset_index(cards: &[u8]) -> usize {
+    cards.iter().enumerate()
+        .map(|(i, &c)| BINOM[c as usize][i + 1] as usize)
+        .sum()
+}
+
+/// Dense ids for suit-isomorphism classes of 5-card hands.
+///
+/// `ids` maps every `comb_index` to its class id, so a canonical lookup is a
+/// single array read. Ids follow the lexicographic order of the canonical
+/// hands, which is the order `precompute` has always used.
+struct CanonicalIndex {
+    ids: Vec<u32>,
+    hands: Vec<([u8; 5], u32)>,
+}
+
+impl CanonicalIndex {
+    fn build() -> Self {
+        let mut ids = vec![u32::MAX; BINOM[52][5] as usize];
+        let mut hands: Vec<([u8; 5], u32)> = Vec::with_capacity(135_000);
+        // Lexicographic enumeration visits every class's canonical member
+        // first, since the canonical form is the smallest member.
+        for c0 in 0u8..48 {
+            for c1 in (c0 + 1)..49 {
+                for c2 in (c1 + 1)..50 {
+                    for c3 in (c2 + 1)..51 {
+                        for c4 in (c3 + 1)..52 {
+                            let hand = [c0, c1, c2, c3, c4];
+                            let can = canonicalize(&hand);
+                            let id = if can == hand {
+                                hands.push((hand, 0));
+                                (hands.len() - 1) as u32
+                            } else {
+                                ids[comb_index(&can)]
+                            };
+                            ids[comb_index(&hand)] = id;
+                            hands[id as usize].1 += 1;
+                        }
+                    }
                 }
             }
         }
+        CanonicalIndex { ids, hands }
     }
-    best
+
+    fn len(&self) -> usize {
+        self.hands.len()
+    }
+
+    /// Class id in 0..len() of a sorted 5-card hand.
+    fn id(&self, hand: &[u8; 5]) -> usize {
+        self.ids[comb_index(hand)] as usize
+    }
+
 }
 
 fn enumerate_canonical() -> Vec<([u8; 5], u32)> {
-    let mut map: HashMap<[u8; 5], u32> = HashMap::with_capacity(140_000);
-    for c0 in 0u8..48 {
-        for c1 in (c0 + 1)..49 {
-            for c2 in (c1 + 1)..50 {
-                for c3 in (c2 + 1)..51 {
-                    for c4 in (c3 + 1)..52 {
-                        let can = canonicalize(&[c0, c1, c2, c3, c4]);
-                        *map.entry(can).or_insert(0) += 1;
-                    }
+    CanonicalIndex::build().hands
+}
+
+/// Dense ids for suit-isomorphism classes of (hand, `B`-card board) pairs.
+///
+/// A pair is put in its hand's canonical suits first. The relabellings that
+/// still fix a canonical hand only swap suits holding the same ranks in it,
+/// so once the hand's cards are taken out, a board's class depends only on
+/// how many cards each suit has left and which suits may swap. Each such
+/// layout numbers its board classes once, over the colex index of the board
+/// among the 47 unseen cards, and a hand's ids follow every earlier hand's.
+#[allow(dead_code)]
+struct PairIndex<'a, const B: usize> {
+    hands: &'a CanonicalIndex,
+    /// Layout of each canonical hand, by class id.
+    layout: Vec<u16>,
+    /// Board class of every colex board index, per layout.
+    layouts: Vec<Vec<u32>>,
+    /// First pair id of each canonical hand; the last entry is the total.
+    offsets: Vec<u64>,
+}
+
+#[allow(dead_code)]
+impl<'a, const B: usize> PairIndex<'a, B> {
+    fn build(hands: &'a CanonicalIndex) -> Self {
+        let mut keys: HashMap<([u8; 4], [u8; 4]), u16> = HashMap::new();
+        let mut layouts: Vec<Vec<u32>> = Vec::new();
+        let mut class_counts: Vec<u64> = Vec::new();
+        let mut layout = Vec::with_capacity(hands.len());
+        let mut offsets = Vec::with_capacity(hands.len() + 1);
+        offsets.push(0u64);
+        for (hand, _) in &hands.hands {
+            let key = Self::layout_key(&Self::suit_masks(hand));
+            let id = *keys.entry(key).or_insert_with(|| {
+                let (classes, count) = Self::board_classes(key);
+                layouts.push(classes);
+                class_counts.push(count);
+                (layouts.len() - 1) as u16
+            });
+            layout.push(id);
+            offsets.push(offsets.last().unwrap() + class_counts[id as usize]);
+        }
+        PairIndex { hands, layout, layouts, offsets }
+    }
+
+    /// Number of pair classes; ids are 0..len().
+    fn len(&self) -> u64 {
+        *self.offsets.last().unwrap()
+    }
+
+    /// Class id of a sorted hand and a board that avoids it (any order).
+    fn id(&self, hand: &[u8; 5], board: &[u8; B]) -> u64 {
+        let perm = canonical_suit_perm(&[hand]);
+        let can_hand = apply_suit_perm(hand, &perm);
+        let can_board = apply_suit_perm(board, &perm);
+        let masks = Self::suit_masks(&can_hand);
+        let (counts, _) = Self::layout_key(&masks);
+        let mut suit_start = [0u8; 4];
+        for su in 1..4 {
+            suit_start[su] = suit_start[su - 1] + counts[su - 1];
+        }
+        // Position among the unseen cards, which keeps the board sorted.
+        let unseen: [u8; B] = can_board.map(|c| {
+            let (su, rank) = ((c / 13) as usize, c % 13);
+            debug_assert!(masks[su] & (1 << rank) == 0, "board card {} is in the hand", c);
+            suit_start[su] + rank - (masks[su] & ((1u16 << rank) - 1)).count_ones() as u8
+        });
+        let hand_id = self.hands.id(&can_hand);
+        self.offsets[hand_id] + self.layouts[self.layout[hand_id] as usize][subset_index(&unseen)] as u64
+    }
+
+    /// Rank mask of each suit in `hand`.
+    fn suit_masks(hand: &[u8; 5]) -> [u16; 4] {
+        let mut masks = [0u16; 4];
+        for &c in hand {
+            masks[(c / 13) as usize] |= 1 << (c % 13);
+        }
+        masks
+    }
+
+    /// Cards left in each suit, and for each suit the first suit with the
+    /// same ranks in the hand (the suits it may swap with).
+    fn layout_key(masks: &[u16; 4]) -> ([u8; 4], [u8; 4]) {
+        let counts = masks.map(|m| 13 - m.count_ones() as u8);
+        let blocks: [u8; 4] = std::array::from_fn(|su| (0..su).find(|&t| masks[t] == masks[su]).unwrap_or(su) as u8);
+        (counts, blocks)
+    }
+
+    /// Board class of every colex index over the unseen cards of one layout,
+    /// numbered in order of each class's smallest index, and the class count.
+    fn board_classes((counts, blocks): ([u8; 4], [u8; 4])) -> (Vec<u32>, u64) {
+        let unseen = counts.iter().map(|&n| n as usize).sum::<usize>();
+        let mut suit_start = [0u8; 4];
+        for su in 1..4 {
+            suit_start[su] = suit_start[su - 1] + counts[su - 1];
+        }
+        let suit_of: Vec<usize> = (0..4).flat_map(|su| std::iter::repeat_n(su, counts[su] as usize)).collect();
+        let perms: Vec<&[u8; 4]> = SUIT_PERMS.iter()
+            .filter(|perm| (0..4).all(|su| blocks[perm[su] as usize] == blocks[su]))
+            .collect();
+
+        let mut classes = vec![u32::MAX; BINOM[unseen][B] as usize];
+        let mut idx: [u8; B] = std::array::from_fn(|i| i as u8);
+        'enumerate: loop {
+            let smallest = perms.iter().map(|perm| {
+                let mut mapped = idx.map(|c| {
+                    let su = suit_of[c as usize];
+                    suit_start[perm[su] as usize] + c - suit_start[su]
+                });
+                mapped.sort_unstable();
+                subset_index(&mapped)
+            }).min().unwrap();
+            classes[subset_index(&idx)] = smallest as u32;
+
+            let mut i = B;
+            loop {
+                if i == 0 {
+                    break 'enumerate;
+                }
+                i -= 1;
+                if (idx[i] as usize) < unseen - B + i {
+                    break;
                 }
             }
+            idx[i] += 1;
+            for j in (i + 1)..B {
+                idx[j] = idx[j - 1] + 1;
+            }
+        }
+
+        // Every class's smallest index is numbered before its other members.
+        let mut count = 0u32;
+        for k in 0..classes.len() {
+            let smallest = classes[k] as usize;
+            classes[k] = if smallest == k {
+                count += 1;
+                count - 1
+            } else {
+                classes[smallest]
+            };
+        }
+        (classes, count as u64)
+    }
+}
+
+/// Class key in 0..C(52, B) of `board` under the suit permutations that keep
+/// every group in `fixed` (hero hand, current board, dead cards) in place.
+fn board_class_key<const B: usize>(fixed: &[&[u8]], board: &[u8; B]) -> usize {
+    let mut groups: Vec<&[u8]> = fixed.to_vec();
+    groups.push(board);
+    subset_index(&apply_suit_perm(board, &canonical_suit_perm(&groups)))
+}
+
+/// Suit-isomorphism classes of the `B`-card boards that can still be dealt
+/// around the `fixed` groups. Each entry is a representative board (sorted,
+/// in the original suits) and the number of boards in its class, so the
+/// weights sum to C(unseen, B).
+fn board_classes<const B: usize>(fixed: &[&[u8]]) -> Vec<([u8; B], u32)> {
+    let used_bm = fixed.iter().fold(0u64, |bm, g| bm | card_bitmap(g));
+    let pool: Vec<u8> = (0..52u8).filter(|&c| used_bm & (1u64 << c) == 0).collect();
+    let mut classes: Vec<([u8; B], u32)> = Vec::new();
+    if pool.len() < B {
+        return classes;
+    }
+    let mut slot = vec![u32::MAX; BINOM[52][B] as usize];
+    let mut idx: [usize; B] = std::array::from_fn(|i| i);
+    loop {
+        let board: [u8; B] = std::array::from_fn(|i| pool[idx[i]]);
+        let key = board_class_key(fixed, &board);
+        if slot[key] == u32::MAX {
+            slot[key] = classes.len() as u32;
+            classes.push((board, 0));
+        }
+        classes[slot[key] as usize].1 += 1;
+
+        let mut i = B;
+        loop {
+            if i == 0 {
+                return classes;
+            }
+            i -= 1;
+            if idx[i] < pool.len() - B + i {
+                break;
+            }
+        }
+        idx[i] += 1;
+        for j in (i + 1)..B {
+            idx[j] = idx[j - 1] + 1;
+        }
+    }
+}
+
+/// All 22,100 flops, ordered so each suit-isomorphism class is contiguous.
+fn flop_strata_order() -> Vec<[u8; 3]> {
+    let mut flops: Vec<(usize, [u8; 3])> = Vec::with_capacity(BINOM[52][3] as usize);
+    for a in 0..50u8 {
+        for b in (a + 1)..51 {
+            for c in (b + 1)..52 {
+                let flop = [a, b, c];
+                flops.push((board_class_key(&[], &flop), flop));
+            }
+        }
+    }
+    flops.sort();
+    flops.into_iter().map(|(_, flop)| flop).collect()
+}
+
+/// The C(47,3) flops around `hero`, weakest to strongest hero flop first.
+/// Hero's flop strength drives most of the board-to-board spread in equity,
+/// so it makes a better stratification key than texture alone.
+fn hero_flop_order(hero: &[u8; 5], table: &[u16]) -> Vec<[u8; 3]> {
+    let hero_2s = two_card_subsets(hero);
+    let remaining: Vec<u8> = (0..52u8).filter(|c| !hero.contains(c)).collect();
+    let mut flops: Vec<(u16, [u8; 3])> = Vec::with_capacity(BINOM[47][3] as usize);
+    for (i, &a) in remaining.iter().enumerate() {
+        for (j, &b) in remaining.iter().enumerate().skip(i + 1) {
+            for &c in &remaining[j + 1..] {
+                let flop = [a, b, c];
+                let mut hand = [0u8; 5];
+                let rank = hero_2s.iter().map(|pair| {
+                    merge5(pair, &flop, &mut hand);
+                    table[comb_index(&hand)]
+                }).min().unwrap();
+                flops.push((rank, flop));
+            }
         }
     }
-    let mut hands: Vec<([u8; 5], u32)> = map.into_iter().collect();
-    hands.sort();
-    hands
+    flops.sort_unstable_by(|a, b| b.cmp(a));
+    flops.into_iter().map(|(_, flop)| flop).collect()
+}
+
+/// `n` flops that avoid `excluded_bm`, taken by systematic sampling along
+/// `order` (one random offset, then an even stride). Every flop keeps the
+/// same inclusion probability, so estimates stay unbiased, while each flop
+/// class gets its proportional share of the boards instead of a random one.
+fn stratified_flops<R: RandomSource>(order: &[[u8; 3]], excluded_bm: u64, n: usize, rng: &mut R) -> Vec<[u8; 3]> {
+    let live: Vec<[u8; 3]> = order.iter().copied()
+        .filter(|flop| card_bitmap(flop) & excluded_bm == 0)
+        .collect();
+    let offset = (rng.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+    let stride = live.len() as f64 / n as f64;
+    (0..n).map(|i| live[(((i as f64 + offset) * stride) as usize).min(live.len() - 1)]).collect()
 }
 
 fn two_card_subsets(hand: &[u8; 5]) -> [[u8; 2]; 10] {
@@ -243,6 +658,9 @@ fn three_card_subsets(board: &[u8; 5]) -> [[u8; 3]; 10] {
     subs
 }
 
+/// Three-card subsets of a 3-5 card board. Flop and turn boards have fewer
+/// than ten, so the remaining slots repeat the first subset; `eval_best`
+/// takes a minimum and is unaffected by the repeats.
 fn three_card_subsets_from_slice(board: &[u8]) -> [[u8; 3]; 10] {
     let mut subs = [[0u8; 3]; 10];
     let mut idx = 0;
@@ -257,6 +675,9 @@ fn three_card_subsets_from_slice(board: &[u8]) -> [[u8; 3]; 10] {
             }
         }
     }
+    for i in idx..10 {
+        subs[i] = subs[0];
+    }
     subs
 }
 
@@ -286,20 +707,342 @@ fn eval_best(hero_2s: &[[u8; 2]; 10], board_3s: &[[u8; 3]; 10], table: &[u16]) -
     best
 }
 
+/// Best Omaha rank (exactly two hole cards, three board cards) of a hand on a
+/// complete board, in eval-table units: 0 is a royal flush.
+trait OmahaEvaluator: Sync {
+    fn name(&self) -> &'static str;
+    /// Both hand and board sorted.
+    fn best_rank(&self, hand: &[u8; 5], board: &[u8; 5]) -> u16;
+    /// The eval table the evaluator was built from, for lookups that are not
+    /// full showdowns (flop orderings, prepared boards).
+    fn table(&self) -> &[u16];
+}
+
+/// The original evaluator: all 100 two-plus-three combinations through
+/// `comb_index` into the 2.6M-entry eval table.
+struct LookupEvaluator<'a> {
+    table: &'a [u16],
+}
+
+impl OmahaEvaluator for LookupEvaluator<'_> {
+    fn name(&self) -> &'static str { "lookup" }
+
+    fn best_rank(&self, hand: &[u8; 5], board: &[u8; 5]) -> u16 {
+        eval_best(&two_card_subsets(hand), &three_card_subsets(board), self.table)
+    }
+
+    fn table(&self) -> &[u16] { self.table }
+}
+
+/// Evaluates by rank instead of by card. Without a flush, a combination's
+/// rank depends only on the two hole ranks and three board ranks, so the
+/// 100 combinations are lookups into a small table indexed by
+/// `pair * 13^3 + triple`, with no sorting or `comb_index`. Flushes are only
+/// checked for a suit with three board cards and two hole cards, through a
+/// table keyed by the 13-bit rank mask.
+struct RankMaskEvaluator<'a> {
+    table: &'a [u16],
+    unsuited: Vec<u16>,
+    flush: Vec<u16>,
+}
+
+impl<'a> RankMaskEvaluator<'a> {
+    fn new(table: &'a [u16]) -> Self {
+        let mut unsuited = vec![u16::MAX; 169 * 2197];
+        for (key, slot) in unsuited.iter_mut().enumerate() {
+            let ranks = [key / 28561, key / 2197 % 13, key / 169 % 13, key / 13 % 13, key % 13];
+            let mut seen = [0u8; 13];
+            let mut cards = [0u8; 5];
+            for (card, &r) in cards.iter_mut().zip(&ranks) {
+                if seen[r] == 4 { break; }
+                *card = seen[r] * 13 + r as u8;
+                seen[r] += 1;
+            }
+            if seen.iter().map(|&n| n as usize).sum::<usize>() < 5 { continue; }
+            if seen.iter().all(|&n| n <= 1) {
+                // Five distinct ranks would all land in suit 0; break the flush.
+                cards[4] += 13;
+            }
+            cards.sort();
+            *slot = table[comb_index(&cards)];
+        }
+
+        let mut flush = vec![u16::MAX; 1 << 13];
+        for (mask, slot) in flush.iter_mut().enumerate() {
+            if (mask as u16).count_ones() != 5 { continue; }
+            let cards: Vec<u8> = (0..13u8).filter(|&r| mask & (1 << r) != 0).collect();
+            *slot = table[comb_index(&cards.try_into().unwrap())];
+        }
+        RankMaskEvaluator { table, unsuited, flush }
+    }
+}
+
+impl OmahaEvaluator for RankMaskEvaluator<'_> {
+    fn name(&self) -> &'static str { "rank_mask" }
+
+    fn best_rank(&self, hand: &[u8; 5], board: &[u8; 5]) -> u16 {
+        let hr = hand.map(|c| (c % 13) as usize);
+        let br = board.map(|c| (c % 13) as usize);
+        let mut pairs = [0usize; 10];
+        let mut triples = [0usize; 10];
+        let mut idx = 0;
+        for i in 0..4 {
+            for j in (i + 1)..5 {
+                pairs[idx] = (hr[i] * 13 + hr[j]) * 2197;
+                idx += 1;
+            }
+        }
+        idx = 0;
+        for i in 0..3 {
+            for j in (i + 1)..4 {
+                for k in (j + 1)..5 {
+                    triples[idx] = br[i] * 169 + br[j] * 13 + br[k];
+                    idx += 1;
+                }
+            }
+        }
+        let mut best = u16::MAX;
+        for &p in &pairs {
+            for &t in &triples {
+                best = best.min(self.unsuited[p + t]);
+            }
+        }
+
+        for suit in 0..4u8 {
+            let board_mask = board.iter().filter(|&&c| c / 13 == suit).fold(0u16, |m, &c| m | (1 << (c % 13)));
+            let hand_mask = hand.iter().filter(|&&c| c / 13 == suit).fold(0u16, |m, &c| m | (1 << (c % 13)));
+            if board_mask.count_ones() < 3 || hand_mask.count_ones() < 2 { continue; }
+            let two = two_card_subsets(hand);
+            let three = three_card_subsets(board);
+            for h in two.iter().filter(|h| h.iter().all(|&c| c / 13 == suit)) {
+                for b in three.iter().filter(|b| b.iter().all(|&c| c / 13 == suit)) {
+                    let mask = h.iter().chain(b).fold(0usize, |m, &c| m | (1 << (c % 13)));
+                    best = best.min(self.flush[mask]);
+                }
+            }
+        }
+        best
+    }
+
+    fn table(&self) -> &[u16] { self.table }
+}
+
+/// Selected in `main` by `--evaluator rank_mask`; the lookup evaluator otherwise.
+static RANK_MASK_EVAL: AtomicBool = AtomicBool::new(false);
+
+fn parse_evaluator(value: &str) -> Result<bool, String> {
+    match value {
+        "rank_mask" | "rank-mask" => Ok(true),
+        "lookup" | "default" => Ok(false),
+        _ => Err(format!("Invalid --evaluator '{}' (expected lookup or rank_mask)", value)),
+    }
+}
+
+/// The evaluator chosen by `--evaluator`, built over `table`.
+fn selected_evaluator(table: &[u16]) -> Box<dyn OmahaEvaluator + '_> {
+    if RANK_MASK_EVAL.load(Ordering::Relaxed) {
+        Box::new(RankMaskEvaluator::new(table))
+    } else {
+        Box::new(LookupEvaluator { table })
+    }
+}
+
+/// A complete board with the best rank of every 2-card holding on it
+/// precomputed, so evaluating a 5-card hand is ten lookups instead of the
+/// hundred `eval_best` needs. Building one ranks all 1,081 pairs off the
+/// board at ten lookups each, about 108 `eval_best` calls, so it pays off
+/// once a board is shared by more than about 120 hands.
+struct PreparedBoard {
+    /// Indexed by the colex index of the pair; pairs touching the board stay `u16::MAX`.
+    pair_ranks: Vec<u16>,
+}
+
+impl PreparedBoard {
+    fn new(board: &[u8; 5], table: &[u16]) -> Self {
+        let board_3s = three_card_subsets(board);
+        let board_bm = card_bitmap(board);
+        let mut pair_ranks = vec![u16::MAX; BINOM[52][2] as usize];
+        for b in 1..52u8 {
+            if board_bm & (1u64 << b) != 0 { continue; }
+            for a in 0..b {
+                if board_bm & (1u64 << a) != 0 { continue; }
+                pair_ranks[Self::pair_index(a, b)] = pair_rank(&[a, b], &board_3s, table);
+            }
+        }
+        PreparedBoard { pair_ranks }
+    }
+
+    fn pair_index(lo: u8, hi: u8) -> usize {
+        hi as usize * (hi as usize - 1) / 2 + lo as usize
+    }
+
+    /// Best rank of two hole cards on this board; `a` and `b` may come in either order.
+    fn pair(&self, a: u8, b: u8) -> u16 {
+        self.pair_ranks[Self::pair_index(a.min(b), a.max(b))]
+    }
+
+    fn best_rank(&self, hand: &[u8; 5]) -> u16 {
+        let mut best = u16::MAX;
+        for i in 0..4 {
+            for j in (i + 1)..5 {
+                best = best.min(self.pair(hand[i], hand[j]));
+            }
+        }
+        best
+    }
+}
+
+/// Source of random words for every sampler. `gen_range` is unbiased by
+/// default (Lemire's multiply-shift with rejection).
+trait RandomSource {
+    fn next_u64(&mut self) -> u64;
+    fn gen_range(&mut self, n: usize) -> usize {
+        let n = n as u64;
+        let mut m = self.next_u64() as u128 * n as u128;
+        if (m as u64) < n {
+            let threshold = n.wrapping_neg() % n;
+            while (m as u64) < threshold {
+                m = self.next_u64() as u128 * n as u128;
+            }
+        }
+        (m >> 64) as usize
+    }
+}
+
+/// The original generator, kept bit-for-bit (including the `% n` range
+/// reduction). `--rng legacy` swaps this in for xoshiro256++ and restores the
+/// old per-thread streams in equity and breakdown, so a seed reproduces the
+/// output of older engines.
+#[derive(Clone)]
 struct Xorshift64 { state: u64 }
 
 impl Xorshift64 {
     fn new(seed: u64) -> Self {
         Xorshift64 { state: if seed == 0 { 1 } else { seed } }
     }
-    fn next(&mut self) -> u64 {
+}
+
+impl RandomSource for Xorshift64 {
+    fn next_u64(&mut self) -> u64 {
         self.state ^= self.state << 13;
         self.state ^= self.state >> 7;
         self.state ^= self.state << 17;
         self.state
     }
     fn gen_range(&mut self, n: usize) -> usize {
-        (self.next() % n as u64) as usize
+        (self.next_u64() % n as u64) as usize
+    }
+}
+
+/// xoshiro256++ (Blackman & Vigna). `jump` advances 2^128 steps, which
+/// splits one seed into non-overlapping parallel streams.
+#[derive(Clone)]
+struct Xoshiro256pp { s: [u64; 4] }
+
+impl Xoshiro256pp {
+    fn from_seed(seed: u64) -> Self {
+        let mut sm = seed;
+        let mut s = [0u64; 4];
+        for word in s.iter_mut() {
+            sm = sm.wrapping_add(0x9e3779b97f4a7c15);
+            let mut z = sm;
+            z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+            *word = z ^ (z >> 31);
+        }
+        Xoshiro256pp { s }
+    }
+
+    fn jump(&mut self) {
+        const JUMP: [u64; 4] = [0x180ec6d33cfd0aba, 0xd5a61266f0c9392c, 0xa9582618e03fc9aa, 0x39abdc4529b1661c];
+        let mut acc = [0u64; 4];
+        for &word in &JUMP {
+            for bit in 0..64 {
+                if word & (1u64 << bit) != 0 {
+                    for (a, s) in acc.iter_mut().zip(self.s.iter()) { *a ^= s; }
+                }
+                self.next_u64();
+            }
+        }
+        self.s = acc;
+    }
+}
+
+impl RandomSource for Xoshiro256pp {
+    fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[0].wrapping_add(s[3]).rotate_left(23).wrapping_add(s[0]);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+}
+
+/// Selected in `main` by `--rng legacy`; xoshiro256++ otherwise.
+static LEGACY_RNG: AtomicBool = AtomicBool::new(false);
+
+fn parse_rng(value: &str) -> Result<bool, String> {
+    match value {
+        "legacy" | "xorshift" => Ok(true),
+        "xoshiro" | "xoshiro256" | "default" => Ok(false),
+        _ => Err(format!("Invalid --rng '{}' (expected legacy or xoshiro)", value)),
+    }
+}
+
+/// The generator every sampler draws from, chosen by `--rng`.
+#[derive(Clone)]
+enum EngineRng {
+    Legacy(Xorshift64),
+    Xoshiro(Xoshiro256pp),
+}
+
+impl EngineRng {
+    fn seeded(seed: u64) -> Self {
+        if LEGACY_RNG.load(Ordering::Relaxed) {
+            EngineRng::Legacy(Xorshift64::new(seed))
+        } else {
+            EngineRng::Xoshiro(Xoshiro256pp::from_seed(seed))
+        }
+    }
+
+    /// `n` independent streams for one seed: successive jumps of a single
+    /// xoshiro state, or the old per-stream seeds from `legacy_seed` when
+    /// reproducing legacy output.
+    fn streams(seed: u64, n: usize, legacy_seed: impl Fn(usize) -> u64) -> Vec<EngineRng> {
+        EngineRng::streams_for(LEGACY_RNG.load(Ordering::Relaxed), seed, n, legacy_seed)
+    }
+
+    /// `streams` with the generator given explicitly instead of by `--rng`.
+    fn streams_for(legacy: bool, seed: u64, n: usize, legacy_seed: impl Fn(usize) -> u64) -> Vec<EngineRng> {
+        if legacy {
+            return (0..n).map(|i| EngineRng::Legacy(Xorshift64::new(legacy_seed(i)))).collect();
+        }
+        let mut base = Xoshiro256pp::from_seed(seed);
+        (0..n).map(|_| {
+            let stream = base.clone();
+            base.jump();
+            EngineRng::Xoshiro(stream)
+        }).collect()
+    }
+}
+
+impl RandomSource for EngineRng {
+    fn next_u64(&mut self) -> u64 {
+        match self {
+            EngineRng::Legacy(rng) => rng.next_u64(),
+            EngineRng::Xoshiro(rng) => rng.next_u64(),
+        }
+    }
+    fn gen_range(&mut self, n: usize) -> usize {
+        match self {
+            EngineRng::Legacy(rng) => rng.gen_range(n),
+            EngineRng::Xoshiro(rng) => rng.gen_range(n),
+        }
     }
 }
 
@@ -319,7 +1062,7 @@ fn mix_seed(seed: u64) -> u64 {
     if s == 0 { 1 } else { s }
 }
 
-fn sample_villain(pool: &[u8], rng: &mut Xorshift64) -> [u8; 5] {
+fn sample_villain<R: RandomSource>(pool: &[u8], rng: &mut R) -> [u8; 5] {
     let n = pool.len();
     let mut indices = [0usize; 5];
     let mut buf = [0u8; 5];
@@ -328,8 +1071,8 @@ fn sample_villain(pool: &[u8], rng: &mut Xorshift64) -> [u8; 5] {
         loop {
             let idx = rng.gen_range(n);
             let mut dup = false;
-            for j in 0..i {
-                if indices[j] == idx { dup = true; break; }
+            for &prev in &indices[..i] {
+                if prev == idx { dup = true; break; }
             }
             if !dup { indices[i] = idx; break; }
         }
@@ -339,11 +1082,131 @@ fn sample_villain(pool: &[u8], rng: &mut Xorshift64) -> [u8; 5] {
     buf
 }
 
+/// Worker threads for parallel sections, set once in `main` from `--threads`;
+/// 0 means auto.
+static THREAD_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// CPUs this process may actually use: the scheduler affinity mask, capped
+/// by a cgroup CPU quota when the container sets one.
 fn num_cpus() -> usize {
-    std::fs::read_to_string("/proc/cpuinfo")
-        .map(|s| s.matches("processor").count())
-        .unwrap_or(4)
-        .max(1)
+    let affinity = thread::available_parallelism().map(|n| n.get()).unwrap_or_else(|_| {
+        fs::read_to_string("/proc/cpuinfo")
+            .map(|s| s.matches("processor").count())
+            .unwrap_or(4)
+    });
+    cgroup_cpu_quota().map_or(affinity, |quota| affinity.min(quota)).max(1)
+}
+
+/// Whole CPUs allowed by the cgroup quota (v2 `cpu.max`, or v1
+/// `cpu.cfs_quota_us` / `cpu.cfs_period_us`), rounded up. The process's own
+/// cgroup comes from `/proc/self/cgroup`, and the tightest quota on the way
+/// from it up to the mount root wins, so nested containers are honored.
+fn cgroup_cpu_quota() -> Option<usize> {
+    let own = fs::read_to_string("/proc/self/cgroup").unwrap_or_default();
+    let (v2_path, v1_path) = parse_proc_cgroup(&own);
+    let quota = |q: f64, period: f64| (q > 0.0 && period > 0.0).then(|| (q / period).ceil() as usize);
+    let v2 = cgroup_ancestors("/sys/fs/cgroup", v2_path.as_deref()).into_iter().filter_map(|dir| {
+        let s = fs::read_to_string(format!("{}/cpu.max", dir)).ok()?;
+        let mut parts = s.split_whitespace();
+        let (q, p) = (parts.next()?, parts.next()?);
+        if q == "max" { return None; }
+        quota(q.parse().ok()?, p.parse().ok()?)
+    }).min();
+    if v2.is_some() { return v2; }
+    ["/sys/fs/cgroup/cpu", "/sys/fs/cgroup/cpu,cpuacct"].iter()
+        .flat_map(|mount| cgroup_ancestors(mount, v1_path.as_deref()))
+        .filter_map(|dir| {
+            let read = |f: &str| fs::read_to_string(format!("{}/{}", dir, f)).ok()?.trim().parse::<f64>().ok();
+            quota(read("cpu.cfs_quota_us")?, read("cpu.cfs_period_us")?)
+        })
+        .min()
+}
+
+/// The v2 unified path (`0::/path`) and the v1 `cpu` controller path
+/// (`N:cpu,cpuacct:/path`) from the contents of `/proc/self/cgroup`.
+fn parse_proc_cgroup(contents: &str) -> (Option<String>, Option<String>) {
+    let (mut v2, mut v1) = (None, None);
+    for line in contents.lines() {
+        let mut fields = line.splitn(3, ':');
+        let (Some(id), Some(controllers), Some(path)) = (fields.next(), fields.next(), fields.next()) else { continue };
+        if id == "0" && controllers.is_empty() {
+            v2 = Some(path.to_string());
+        } else if controllers.split(',').any(|c| c == "cpu") {
+            v1 = Some(path.to_string());
+        }
+    }
+    (v2, v1)
+}
+
+/// `mount` joined with `path` and each of its ancestors, deepest first,
+/// ending at `mount` itself.
+fn cgroup_ancestors(mount: &str, path: Option<&str>) -> Vec<String> {
+    let mut dirs = Vec::new();
+    let mut path = path.unwrap_or("").trim_end_matches('/');
+    while !path.is_empty() {
+        dirs.push(format!("{}{}", mount, path));
+        path = &path[..path.rfind('/').unwrap_or(0)];
+    }
+    dirs.push(mount.to_string());
+    dirs
+}
+
+/// Parses `--threads auto|N`; auto (the default) uses `num_cpus`.
+fn parse_threads(value: &str) -> Result<usize, String> {
+    match value {
+        "auto" => Ok(0),
+        s => match s.parse::<usize>() {
+            Ok(n) if n > 0 => Ok(n),
+            _ => Err(format!("Invalid --threads value: {}. Use 'auto' or a positive number.", s)),
+        },
+    }
+}
+
+fn num_threads() -> usize {
+    match THREAD_COUNT.load(Ordering::Relaxed) {
+        0 => num_cpus(),
+        n => n,
+    }
+}
+
+thread_local! {
+    /// Set on `parallel_map` worker threads, so nested calls run inline
+    /// instead of starting threads of their own.
+    static IN_WORKER: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Runs `task(0..num_tasks)` on `num_threads()` scoped worker threads and
+/// returns the results in task order. Workers pull the next task index from
+/// a shared counter, so uneven tasks still keep every thread busy. Called
+/// from inside a task, it runs the tasks inline.
+fn parallel_map<T: Send>(num_tasks: usize, task: impl Fn(usize) -> T + Sync) -> Vec<T> {
+    parallel_map_with(num_threads(), num_tasks, task)
+}
+
+/// `parallel_map` on an explicit number of threads.
+fn parallel_map_with<T: Send>(threads: usize, num_tasks: usize, task: impl Fn(usize) -> T + Sync) -> Vec<T> {
+    let workers = threads.min(num_tasks);
+    if workers <= 1 || IN_WORKER.with(|w| w.get()) {
+        return (0..num_tasks).map(task).collect();
+    }
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<(usize, T)> = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers).map(|_| scope.spawn(|| {
+            IN_WORKER.with(|w| w.set(true));
+            let mut local = Vec::new();
+            loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                if i >= num_tasks { break; }
+                local.push((i, task(i)));
+            }
+            local
+        })).collect();
+        handles.into_iter()
+            .flat_map(|h| h.join().unwrap_or_else(|e| std::panic::resume_unwind(e)))
+            .collect()
+    });
+    results.sort_by_key(|&(i, _)| i);
+    results.into_iter().map(|(_, r)| r).collect()
 }
 
 fn card_name(card: u8) -> String {
@@ -365,35 +1228,204 @@ fn parse_flag(args: &[String], flag: &str) -> Option<String> {
     None
 }
 
-fn run_precompute(args: &[String]) {
-    let boards_str = parse_flag(args, "--boards").unwrap_or_else(|| "full".into());
-    let villain_samples: u32 = parse_flag(args, "--villain-samples")
-        .and_then(|s| s.parse().ok()).unwrap_or(50);
-    let threads_str = parse_flag(args, "--threads").unwrap_or_else(|| "auto".into());
-    let output = parse_flag(args, "--out").unwrap_or_else(|| "plo5_rankings_prod.bin".into());
-    let seed: u64 = parse_flag(args, "--seed")
-        .and_then(|s| s.parse().ok()).unwrap_or(12345);
-    let crn_mode = parse_flag(args, "--crn")
-        .map(|s| s == "on" || s == "yes" || s == "true")
-        .unwrap_or(true);
+/// `s` as the inside of a JSON string literal.
+fn json_escape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out
+}
 
-    let full_enum = boards_str == "full";
-    let boards_per_hero: u32 = if full_enum {
-        BINOM[47][5]
-    } else {
+/// Report a command error and exit: `{"ok":false,...}` on stdout with status
+/// 0 for `--json` callers, plain text on stderr with status 1 otherwise.
+/// Messages often quote user input, so the JSON form escapes them.
+fn fail(json: bool, msg: String) -> ! {
+    if json {
+        println!("{{\"ok\":false,\"error\":\"{}\"}}", json_escape(&msg));
+        std::process::exit(0);
+    }
+    eprintln!("{}", msg);
+    std::process::exit(1);
+}
+
+/// Most opponents a hero can face with a full board still dealt.
+const MAX_OPPONENTS: u32 = 8;
+
+fn parse_opponents(args: &[String], flag: &str) -> Result<u32, String> {
+    match parse_flag(args, flag) {
+        None => Ok(1),
+        Some(value) => match value.parse::<u32>() {
+            Ok(n) if (1..=MAX_OPPONENTS).contains(&n) => Ok(n),
+            _ => Err(format!("Invalid {} '{}' (expected 1-{})", flag, value, MAX_OPPONENTS)),
+        },
+    }
+}
+
+/// Rank index and rankings file behind `--villain-range`: `--rank-file` and
+/// `--bin` when given, otherwise the defaults for `--range-opponents`, so a
+/// range can be taken from the heads-up or any multiway ranking.
+fn range_files(args: &[String]) -> Result<(String, String), String> {
+    let opponents = parse_opponents(args, "--range-opponents")?;
+    let rank_file = parse_flag(args, "--rank-file")
+        .unwrap_or_else(|| multiway_path("public/rank_index_all_2598960.u32", opponents));
+    let bin_path = parse_flag(args, "--bin")
+        .unwrap_or_else(|| multiway_path("public/plo5_rankings_prod.bin", opponents));
+    match read_opponents_meta(&rank_file) {
+        Some(n) if n != opponents => Err(format!(
+            "{} was ranked against {} opponent(s) but --range-opponents is {}", rank_file, n, opponents)),
+        _ => Ok((rank_file, bin_path)),
+    }
+}
+
+/// Sidecar beside a headerless `.f32` or `.u32` output, since those files
+/// cannot say which opponent count they were computed against.
+fn meta_path(path: &str) -> String {
+    format!("{}.meta", path)
+}
+
+fn write_opponents_meta(path: &str, opponents: u32) {
+    fs::write(meta_path(path), format!("opponents={}\n", opponents)).expect("Cannot write metadata sidecar");
+}
+
+/// Opponent count from `path`'s sidecar, or None for files written before
+/// sidecars existed.
+fn read_opponents_meta(path: &str) -> Option<u32> {
+    fs::read_to_string(meta_path(path)).ok()?
+        .lines()
+        .find_map(|line| line.strip_prefix("opponents="))?
+        .trim()
+        .parse()
+        .ok()
+}
+
+/// Default file name for rankings made against `opponents` random hands:
+/// heads-up keeps the historical names, multiway adds `_<N>way`.
+fn multiway_path(path: &str, opponents: u32) -> String {
+    if opponents <= 1 {
+        return path.to_string();
+    }
+    with_suffix(path, &format!("_{}way", opponents))
+}
+
+/// Hero's pot share against `opponents` random hands dealt from `pool`: 1
+/// for a win, 1/k in a k-way split, 0 when anyone beats hero. Heads-up draws
+/// exactly as it always has, so one-opponent results are unchanged.
+fn multiway_share<R: RandomSource>(hero_rank: u16, pool: &[u8], opponents: u32, rng: &mut R,
+                                   rank_of: impl Fn(&[u8; 5]) -> u16) -> f64 {
+    if opponents == 1 {
+        let villain_rank = rank_of(&sample_villain(pool, rng));
+        return if hero_rank < villain_rank { 1.0 } else if hero_rank == villain_rank { 0.5 } else { 0.0 };
+    }
+    let cards = sample_n(pool, 5 * opponents as usize, rng);
+    let mut split = 1u32;
+    for chunk in cards.chunks_exact(5) {
+        let mut villain: [u8; 5] = chunk.try_into().unwrap();
+        villain.sort();
+        let villain_rank = rank_of(&villain);
+        if villain_rank < hero_rank {
+            return 0.0;
+        }
+        if villain_rank == hero_rank {
+            split += 1;
+        }
+    }
+    1.0 / split as f64
+}
+
+/// Hero equity against `opponents` random hands over `boards` random runouts
+/// with `villain_samples` deals each, as (equity sum, showdowns). When
+/// `stratified`, the flops are spread along `hero_flop_order` by
+/// `stratified_flops` and only the turn and river are drawn at random.
+fn sample_hero_equity<R: RandomSource>(hero: &[u8; 5], boards: u32, villain_samples: u32, opponents: u32,
+                                       stratified: bool, rng: &mut R, eval: &dyn OmahaEvaluator) -> (f64, u64) {
+    let remaining: Vec<u8> = (0..52u8).filter(|c| !hero.contains(c)).collect();
+    let flops = stratified.then(|| stratified_flops(&hero_flop_order(hero, eval.table()), 0, boards as usize, rng));
+    let mut equity_sum = 0.0f64;
+    let mut count = 0u64;
+    for b in 0..boards as usize {
+        let board: [u8; 5] = match &flops {
+            Some(flops) => {
+                let flop = flops[b];
+                let rest: Vec<u8> = remaining.iter().copied().filter(|c| !flop.contains(c)).collect();
+                let turn_river = sample_n(&rest, 2, rng);
+                [flop[0], flop[1], flop[2], turn_river[0], turn_river[1]]
+            }
+            None => sample_villain(&remaining, rng),
+        };
+        let mut sorted_board = board;
+        sorted_board.sort();
+        let hero_rank = eval.best_rank(hero, &sorted_board);
+
+        let pool: Vec<u8> = remaining.iter()
+            .copied().filter(|c| !board.contains(c)).collect();
+
+        for _ in 0..villain_samples {
+            equity_sum += multiway_share(hero_rank, &pool, opponents, rng,
+                |villain| eval.best_rank(villain, &sorted_board));
+            count += 1;
+        }
+    }
+    (equity_sum, count)
+}
+
+/// CRN boards whose pair-rank tables precompute keeps at once (2.6 KB each).
+const PRECOMPUTE_BOARD_BATCH: usize = 1024;
+
+fn run_precompute(args: &[String]) {
+    let boards_str = parse_flag(args, "--boards").unwrap_or_else(|| "full".into());
+    let villain_samples_str = parse_flag(args, "--villain-samples").unwrap_or_else(|| "50".into());
+    let exact_villains = villain_samples_str == "exact";
+    // 0 in the header marks exact villain counting.
+    let villain_samples: u32 = if exact_villains { 0 } else { villain_samples_str.parse().unwrap_or(50) };
+    let opponents = parse_opponents(args, "--opponents").unwrap_or_else(|msg| {
+        eprintln!("{}", msg);
+        std::process::exit(1);
+    });
+    let output = parse_flag(args, "--out")
+        .unwrap_or_else(|| multiway_path("plo5_rankings_prod.bin", opponents));
+    let seed: u64 = parse_flag(args, "--seed")
+        .and_then(|s| s.parse().ok()).unwrap_or(12345);
+    let crn_mode = parse_flag(args, "--crn")
+        .map(|s| s == "on" || s == "yes" || s == "true")
+        .unwrap_or(true);
+    let sampling = parse_flag(args, "--sampling").unwrap_or_else(|| "random".into());
+    let stratified = match sampling.as_str() {
+        "random" => false,
+        "stratified" => true,
+        _ => {
+            eprintln!("Invalid --sampling value: {}. Use 'random' or 'stratified'.", sampling);
+            std::process::exit(1);
+        }
+    };
+
+    let full_enum = boards_str == "full";
+    let boards_per_hero: u32 = if full_enum {
+        BINOM[47][5]
+    } else {
         boards_str.parse().unwrap_or_else(|_| {
             eprintln!("Invalid --boards value: {}. Use 'full' or a number of sampled boards.", boards_str);
             std::process::exit(1);
         })
     };
+    if exact_villains && full_enum {
+        eprintln!("--villain-samples exact needs a sampled board count (--boards <N>).");
+        std::process::exit(1);
+    }
+    if exact_villains && opponents > 1 {
+        eprintln!("--villain-samples exact is heads-up only (--opponents 1).");
+        std::process::exit(1);
+    }
 
-    let num_threads: usize = match threads_str.as_str() {
-        "auto" => num_cpus(),
-        s => s.parse().unwrap_or_else(|_| {
-            eprintln!("Invalid --threads value: {}. Use 'auto' or a number.", s);
-            std::process::exit(1);
-        }),
-    };
+    let num_threads = num_threads();
 
     eprintln!("╔══════════════════════════════════════════════╗");
     eprintln!("║       PLO5 Ranker — Precompute Engine        ║");
@@ -401,10 +1433,20 @@ fn run_precompute(args: &[String]) {
     eprintln!();
     eprintln!("  Mode:               {}", if full_enum { "FULL ENUMERATION" } else { "RANDOM BOARD SAMPLING" });
     eprintln!("  Boards per hero:    {}", boards_per_hero);
-    eprintln!("  Villain samples:    {}", villain_samples);
+    if exact_villains {
+        eprintln!("  Villain samples:    EXACT (all C(42,5) holdings counted per board)");
+    } else {
+        eprintln!("  Villain samples:    {}", villain_samples);
+    }
+    eprintln!("  Opponents:          {}{}", opponents, if opponents > 1 { " (multiway pot share)" } else { "" });
     eprintln!("  Seed:               {}", seed);
+    eprintln!("  Flop sampling:      {}", if full_enum { "N/A (full enum)" } else if stratified { "STRATIFIED (systematic over flop classes)" } else { "RANDOM" });
     eprintln!("  CRN mode:           {}", if crn_mode && !full_enum { "ON (shared board scenarios)" } else if full_enum { "N/A (full enum)" } else { "OFF (independent sampling)" });
     eprintln!("  Threads:            {}", num_threads);
+    let crn_active = (crn_mode || exact_villains) && !full_enum;
+    eprintln!("  Evaluator:          {}", if crn_active {
+        "pair tables (CRN boards are prepared; --evaluator unused)"
+    } else if RANK_MASK_EVAL.load(Ordering::Relaxed) { "rank_mask" } else { "lookup" });
     eprintln!("  Output:             {}", output);
     eprintln!();
 
@@ -412,6 +1454,7 @@ fn run_precompute(args: &[String]) {
 
     eprintln!("[1/4] Initializing eval table...");
     let table = init_eval_table();
+    let evaluator = selected_evaluator(&table);
     eprintln!("       {} entries in {:.2}s", table.len(), t0.elapsed().as_secs_f64());
 
     eprintln!("[2/4] Enumerating canonical hands...");
@@ -422,7 +1465,8 @@ fn run_precompute(args: &[String]) {
     eprintln!("       {} canonical hands ({} total combos) in {:.2}s",
         num_hands, total_combos, t1.elapsed().as_secs_f64());
 
-    let evals_per_hero = boards_per_hero as u64 * villain_samples as u64;
+    let villains_per_board = if exact_villains { BINOM[42][5] as u64 } else { villain_samples as u64 };
+    let evals_per_hero = boards_per_hero as u64 * villains_per_board;
     let total_evals = evals_per_hero * num_hands as u64;
     eprintln!();
     eprintln!("[3/4] Computing equity...");
@@ -435,7 +1479,12 @@ fn run_precompute(args: &[String]) {
         eprintln!("           For {} RANDOM 5-card community boards (sampled from 47):", boards_per_hero);
     }
     eprintln!("             pool = 47 - 5 board = 42 cards");
-    eprintln!("             For {} random villain hands (5 cards from pool of 42):", villain_samples);
+    if exact_villains {
+        eprintln!("             Count ALL C(42,5) = {} villain hands by inclusion-exclusion", BINOM[42][5]);
+        eprintln!("               over one ranking of the board's C(47,5) holdings:");
+    } else {
+        eprintln!("             For {} random villain hands (5 cards from pool of 42):", villain_samples);
+    }
     eprintln!("               PLO5 eval: best(C(5,2) hero × C(5,3) board) = best of 100 combos");
     eprintln!("               Compare hero_rank vs villain_rank → win/lose/tie");
     eprintln!("             equity = wins / total_showdowns");
@@ -449,20 +1498,41 @@ fn run_precompute(args: &[String]) {
         villain_seeds: Vec<u64>,
     }
 
-    let crn_active = crn_mode && !full_enum;
+    // CRN boards are shared by every hero, so they can only be stratified by
+    // flop texture; independent sampling stratifies per hero instead.
+    let flop_order: Option<Vec<[u8; 3]>> = (stratified && crn_active).then(flop_strata_order);
     let scenarios: Vec<CrnScenario> = if crn_active {
         eprintln!("       CRN: Pre-generating {} board scenarios from full 52-card deck (seed={})...", boards_per_hero, seed);
-        let mut scenario_rng = Xorshift64::new(seed);
+        let mut scenario_rng = EngineRng::seeded(seed);
         let all52: Vec<u8> = (0..52u8).collect();
-        let sc: Vec<CrnScenario> = (0..boards_per_hero).map(|_| {
-            let board = sample_villain(&all52, &mut scenario_rng);
+        let flops = flop_order.as_ref()
+            .map(|order| stratified_flops(order, 0, boards_per_hero as usize, &mut scenario_rng));
+        let sc: Vec<CrnScenario> = (0..boards_per_hero as usize).map(|b| {
+            let board = match &flops {
+                Some(flops) => {
+                    let flop = flops[b];
+                    let rest: Vec<u8> = all52.iter().copied().filter(|c| !flop.contains(c)).collect();
+                    let turn_river = sample_n(&rest, 2, &mut scenario_rng);
+                    let mut board = [flop[0], flop[1], flop[2], turn_river[0], turn_river[1]];
+                    board.sort();
+                    board
+                }
+                None => sample_villain(&all52, &mut scenario_rng),
+            };
             let board_bm = card_bitmap(&board);
-            let villain_seeds: Vec<u64> = (0..villain_samples)
-                .map(|_| scenario_rng.next())
-                .collect();
+            // Exact mode counts every holding, so it draws no villain seeds.
+            let villain_seeds: Vec<u64> = if exact_villains {
+                Vec::new()
+            } else {
+                (0..villain_samples).map(|_| scenario_rng.next_u64()).collect()
+            };
             CrnScenario { board, board_bm, villain_seeds }
         }).collect();
-        eprintln!("       {} scenarios generated ({} villain seeds each).", sc.len(), villain_samples);
+        if exact_villains {
+            eprintln!("       {} scenarios generated (villains counted exactly).", sc.len());
+        } else {
+            eprintln!("       {} scenarios generated ({} villain seeds each).", sc.len(), villain_samples);
+        }
         eprintln!("       Expected boards/hero after filtering: ~{:.0} ({:.1}%)",
             sc.len() as f64 * 0.5902, 59.02);
         sc
@@ -474,158 +1544,175 @@ fn run_precompute(args: &[String]) {
     let global_boards_total = AtomicU64::new(0);
     let global_showdowns_total = AtomicU64::new(0);
     let t2 = Instant::now();
-    let chunk_size = (num_hands + num_threads - 1) / num_threads;
-
-    let results: Vec<Vec<(f64, u64)>> = thread::scope(|s| {
-        let handles: Vec<_> = (0..num_threads)
-            .map(|t| {
-                let table_ref = &table;
-                let canonical_ref = &canonical;
-                let scenarios_ref = &scenarios;
-                let progress_ref = &progress;
-                let boards_total_ref = &global_boards_total;
-                let showdowns_total_ref = &global_showdowns_total;
-                let full = full_enum;
-                let bph = boards_per_hero;
-                let use_crn = crn_active;
-                let thread_seed = seed.wrapping_add(t as u64).wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
-                s.spawn(move || {
-                    let start = t * chunk_size;
-                    let end = (start + chunk_size).min(num_hands);
-                    let mut thread_results: Vec<(f64, u64)> = Vec::with_capacity(end - start);
-                    let mut rng = Xorshift64::new(thread_seed);
-                    let mut local_boards: u64 = 0;
-                    let mut local_showdowns: u64 = 0;
-
-                    for i in start..end {
-                        let hero = &canonical_ref[i].0;
-                        let hero_2s = two_card_subsets(hero);
-
-                        let mut equity_sum = 0.0f64;
-                        let mut count = 0u64;
-
-                        if use_crn {
-                            let hero_bm = card_bitmap(hero);
-                            for sc in scenarios_ref.iter() {
-                                if (hero_bm & sc.board_bm) != 0 { continue; }
-                                local_boards += 1;
-
-                                let board_3s = three_card_subsets(&sc.board);
-                                let hero_rank = eval_best(&hero_2s, &board_3s, table_ref);
-
-                                let combined_bm = hero_bm | sc.board_bm;
-                                let pool: Vec<u8> = (0..52u8)
-                                    .filter(|c| (combined_bm >> (*c as u64)) & 1 == 0)
-                                    .collect();
-
-                                for &vseed in &sc.villain_seeds {
-                                    let mut vrng = Xorshift64::new(mix_seed(vseed));
-                                    let villain = sample_villain(&pool, &mut vrng);
-                                    let villain_2s = two_card_subsets(&villain);
-                                    let villain_rank = eval_best(&villain_2s, &board_3s, table_ref);
-                                    if hero_rank < villain_rank {
-                                        equity_sum += 1.0;
-                                    } else if hero_rank == villain_rank {
-                                        equity_sum += 0.5;
-                                    }
-                                    count += 1;
-                                    local_showdowns += 1;
-                                }
-                            }
-                        } else if full {
-                            let remaining: Vec<u8> = (0..52u8).filter(|c| !hero.contains(c)).collect();
-                            let rem_len = remaining.len();
-                            for b0 in 0..(rem_len - 4) {
-                                for b1 in (b0 + 1)..(rem_len - 3) {
-                                    for b2 in (b1 + 1)..(rem_len - 2) {
-                                        for b3 in (b2 + 1)..(rem_len - 1) {
-                                            for b4 in (b3 + 1)..rem_len {
-                                                local_boards += 1;
-                                                let mut board = [
-                                                    remaining[b0], remaining[b1], remaining[b2],
-                                                    remaining[b3], remaining[b4],
-                                                ];
-                                                board.sort();
-                                                let board_3s = three_card_subsets(&board);
-                                                let hero_rank = eval_best(&hero_2s, &board_3s, table_ref);
-
-                                                let mut pool = Vec::with_capacity(42);
-                                                for idx in 0..rem_len {
-                                                    if idx != b0 && idx != b1 && idx != b2 && idx != b3 && idx != b4 {
-                                                        pool.push(remaining[idx]);
-                                                    }
-                                                }
-
-                                                for _ in 0..villain_samples {
-                                                    let villain = sample_villain(&pool, &mut rng);
-                                                    let villain_2s = two_card_subsets(&villain);
-                                                    let villain_rank = eval_best(&villain_2s, &board_3s, table_ref);
-                                                    if hero_rank < villain_rank {
-                                                        equity_sum += 1.0;
-                                                    } else if hero_rank == villain_rank {
-                                                        equity_sum += 0.5;
-                                                    }
-                                                    count += 1;
-                                                    local_showdowns += 1;
-                                                }
+    let chunk_size = num_hands.div_ceil(num_threads);
+
+    let thread_rngs = EngineRng::streams(seed, num_threads, |t| {
+        seed.wrapping_add(t as u64).wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407)
+    });
+
+    let results: Vec<Vec<(f64, u64)>> = if exact_villains {
+        // Board-major: rank every holding on a board once, then count each
+        // hero's exact outcomes. Per-hero sums are board equities and counts
+        // are boards, so the samples in the header are boards here.
+        let mut sums = vec![(0.0f64, 0u64); num_hands];
+        let heroes_per_task = num_hands.div_ceil(num_threads * 8).max(1);
+        for (b, sc) in scenarios.iter().enumerate() {
+            let prepared = PreparedBoard::new(&sc.board, &table);
+            let counts = RiverVillainCounts::new(&sc.board, &prepared);
+            let chunks = parallel_map(num_hands.div_ceil(heroes_per_task), |task| {
+                let start = task * heroes_per_task;
+                canonical[start..(start + heroes_per_task).min(num_hands)].iter().map(|(hero, _)| {
+                    if card_bitmap(hero) & sc.board_bm != 0 { return None; }
+                    let (wins, ties, total) = counts.outcomes(hero, prepared.best_rank(hero));
+                    Some((wins as f64 + 0.5 * ties as f64) / total as f64)
+                }).collect::<Vec<_>>()
+            });
+            for (sum, equity) in sums.iter_mut().zip(chunks.into_iter().flatten()) {
+                if let Some(equity) = equity {
+                    sum.0 += equity;
+                    sum.1 += 1;
+                }
+            }
+            let done = b + 1;
+            let elapsed = t2.elapsed().as_secs_f64();
+            let eta = elapsed / done as f64 * (scenarios.len() - done) as f64;
+            eprint!("\r       Progress: {}/{} boards ({:.1}%) ETA: {}h{:02}m{:02}s   ",
+                done, scenarios.len(), done as f64 / scenarios.len() as f64 * 100.0,
+                (eta / 3600.0) as u32, ((eta % 3600.0) / 60.0) as u32, (eta % 60.0) as u32);
+        }
+        let boards_total: u64 = sums.iter().map(|&(_, n)| n).sum();
+        global_boards_total.store(boards_total, Ordering::Relaxed);
+        global_showdowns_total.store(boards_total * BINOM[42][5] as u64, Ordering::Relaxed);
+        vec![sums]
+    } else if crn_active {
+        // Board-major in batches: the workers build each batch's pair-rank
+        // tables, every hero plays the batch, and the tables are dropped
+        // before the next one, so memory does not grow with --boards.
+        // Hero sums carry over between batches in scenario order, so the
+        // result matches a hero-major pass bit for bit.
+        let mut sums = vec![(0.0f64, 0u64); num_hands];
+        let mut boards_total = 0u64;
+        let heroes_per_task = num_hands.div_ceil(num_threads * 8).max(1);
+        let mut done = 0usize;
+        for batch in scenarios.chunks(PRECOMPUTE_BOARD_BATCH) {
+            let prepared: Vec<PreparedBoard> = parallel_map(batch.len(), |i| PreparedBoard::new(&batch[i].board, &table));
+            let chunks = parallel_map(num_hands.div_ceil(heroes_per_task), |task| {
+                let start = task * heroes_per_task;
+                let end = (start + heroes_per_task).min(num_hands);
+                canonical[start..end].iter().zip(&sums[start..end]).map(|((hero, _), &(mut equity_sum, mut count))| {
+                    let hero_bm = card_bitmap(hero);
+                    let mut boards = 0u64;
+                    for (sc, prepared) in batch.iter().zip(&prepared) {
+                        if (hero_bm & sc.board_bm) != 0 { continue; }
+                        boards += 1;
+                        let hero_rank = prepared.best_rank(hero);
+                        let combined_bm = hero_bm | sc.board_bm;
+                        let pool: Vec<u8> = (0..52u8)
+                            .filter(|c| (combined_bm >> (*c as u64)) & 1 == 0)
+                            .collect();
+                        for &vseed in &sc.villain_seeds {
+                            let mut vrng = EngineRng::seeded(mix_seed(vseed));
+                            equity_sum += multiway_share(hero_rank, &pool, opponents, &mut vrng,
+                                |villain| prepared.best_rank(villain));
+                            count += 1;
+                        }
+                    }
+                    ((equity_sum, count), boards)
+                }).collect::<Vec<_>>()
+            });
+            for (sum, (updated, boards)) in sums.iter_mut().zip(chunks.into_iter().flatten()) {
+                *sum = updated;
+                boards_total += boards;
+            }
+            done += batch.len();
+            let elapsed = t2.elapsed().as_secs_f64();
+            let eta = elapsed / done as f64 * (scenarios.len() - done) as f64;
+            eprint!("\r       Progress: {}/{} boards ({:.1}%) ETA: {}h{:02}m{:02}s   ",
+                done, scenarios.len(), done as f64 / scenarios.len() as f64 * 100.0,
+                (eta / 3600.0) as u32, ((eta % 3600.0) / 60.0) as u32, (eta % 60.0) as u32);
+        }
+        global_boards_total.store(boards_total, Ordering::Relaxed);
+        global_showdowns_total.store(boards_total * villain_samples as u64, Ordering::Relaxed);
+        vec![sums]
+    } else {
+        parallel_map(num_threads, |t| {
+            let eval = evaluator.as_ref();
+            let mut rng = thread_rngs[t].clone();
+            let start = t * chunk_size;
+            let end = (start + chunk_size).min(num_hands);
+            let mut thread_results: Vec<(f64, u64)> = Vec::with_capacity(end - start);
+            let mut local_boards: u64 = 0;
+            let mut local_showdowns: u64 = 0;
+
+            for (hero, _) in &canonical[start..end] {
+                let mut equity_sum = 0.0f64;
+                let mut count = 0u64;
+
+                if full_enum {
+                    let remaining: Vec<u8> = (0..52u8).filter(|c| !hero.contains(c)).collect();
+                    let rem_len = remaining.len();
+                    for b0 in 0..(rem_len - 4) {
+                        for b1 in (b0 + 1)..(rem_len - 3) {
+                            for b2 in (b1 + 1)..(rem_len - 2) {
+                                for b3 in (b2 + 1)..(rem_len - 1) {
+                                    for b4 in (b3 + 1)..rem_len {
+                                        local_boards += 1;
+                                        let mut board = [
+                                            remaining[b0], remaining[b1], remaining[b2],
+                                            remaining[b3], remaining[b4],
+                                        ];
+                                        board.sort();
+                                        let hero_rank = eval.best_rank(hero, &board);
+
+                                        let mut pool = Vec::with_capacity(42);
+                                        for (idx, &c) in remaining.iter().enumerate() {
+                                            if idx != b0 && idx != b1 && idx != b2 && idx != b3 && idx != b4 {
+                                                pool.push(c);
                                             }
                                         }
+
+                                        for _ in 0..villain_samples {
+                                            equity_sum += multiway_share(hero_rank, &pool, opponents, &mut rng,
+                                                |villain| eval.best_rank(villain, &board));
+                                            count += 1;
+                                            local_showdowns += 1;
+                                        }
                                     }
                                 }
                             }
-                        } else {
-                            let remaining: Vec<u8> = (0..52u8).filter(|c| !hero.contains(c)).collect();
-                            for _ in 0..bph {
-                                local_boards += 1;
-                                let board = sample_villain(&remaining, &mut rng);
-                                let mut sorted_board = board;
-                                sorted_board.sort();
-                                let board_3s = three_card_subsets(&sorted_board);
-                                let hero_rank = eval_best(&hero_2s, &board_3s, table_ref);
-
-                                let pool: Vec<u8> = remaining.iter()
-                                    .copied().filter(|c| !board.contains(c)).collect();
-
-                                for _ in 0..villain_samples {
-                                    let villain = sample_villain(&pool, &mut rng);
-                                    let villain_2s = two_card_subsets(&villain);
-                                    let villain_rank = eval_best(&villain_2s, &board_3s, table_ref);
-                                    if hero_rank < villain_rank {
-                                        equity_sum += 1.0;
-                                    } else if hero_rank == villain_rank {
-                                        equity_sum += 0.5;
-                                    }
-                                    count += 1;
-                                    local_showdowns += 1;
-                                }
-                            }
-                        }
-
-                        thread_results.push((equity_sum, count));
-                        let done = progress_ref.fetch_add(1, Ordering::Relaxed) + 1;
-                        if done % 1000 == 0 || done == num_hands as u64 {
-                            let elapsed = t2.elapsed().as_secs_f64();
-                            let rate = done as f64 / elapsed;
-                            let eta = (num_hands as f64 - done as f64) / rate;
-                            let eta_h = (eta / 3600.0) as u32;
-                            let eta_m = ((eta % 3600.0) / 60.0) as u32;
-                            let eta_s = (eta % 60.0) as u32;
-                            eprint!(
-                                "\r       Progress: {}/{} ({:.1}%) ETA: {}h{:02}m{:02}s   ",
-                                done, num_hands,
-                                done as f64 / num_hands as f64 * 100.0,
-                                eta_h, eta_m, eta_s
-                            );
                         }
                     }
-                    boards_total_ref.fetch_add(local_boards, Ordering::Relaxed);
-                    showdowns_total_ref.fetch_add(local_showdowns, Ordering::Relaxed);
-                    thread_results
-                })
-            })
-            .collect();
-        handles.into_iter().map(|h| h.join().unwrap()).collect()
-    });
+                } else {
+                    let (sum, n) = sample_hero_equity(hero, boards_per_hero, villain_samples, opponents,
+                        stratified, &mut rng, eval);
+                    equity_sum = sum;
+                    count = n;
+                    local_boards += boards_per_hero as u64;
+                    local_showdowns += n;
+                }
+
+                thread_results.push((equity_sum, count));
+                let done = progress.fetch_add(1, Ordering::Relaxed) + 1;
+                if done.is_multiple_of(1000) || done == num_hands as u64 {
+                    let elapsed = t2.elapsed().as_secs_f64();
+                    let rate = done as f64 / elapsed;
+                    let eta = (num_hands as f64 - done as f64) / rate;
+                    let eta_h = (eta / 3600.0) as u32;
+                    let eta_m = ((eta % 3600.0) / 60.0) as u32;
+                    let eta_s = (eta % 60.0) as u32;
+                    eprint!(
+                        "\r       Progress: {}/{} ({:.1}%) ETA: {}h{:02}m{:02}s   ",
+                        done, num_hands,
+                        done as f64 / num_hands as f64 * 100.0,
+                        eta_h, eta_m, eta_s
+                    );
+                }
+            }
+            global_boards_total.fetch_add(local_boards, Ordering::Relaxed);
+            global_showdowns_total.fetch_add(local_showdowns, Ordering::Relaxed);
+            thread_results
+        })
+    };
 
     eprintln!();
     let compute_elapsed = t2.elapsed().as_secs_f64();
@@ -670,7 +1757,10 @@ fn run_precompute(args: &[String]) {
     f.write_all(&(min_samples as u32).to_le_bytes()).unwrap();
     f.write_all(&(max_samples as u32).to_le_bytes()).unwrap();
     f.write_all(&timestamp.to_le_bytes()).unwrap();
-    f.write_all(&[0u8; 24]).unwrap();
+    f.write_all(&opponents.to_le_bytes()).unwrap();
+    let sharing = if crn_active { BoardSharing::Shared } else { BoardSharing::Independent };
+    f.write_all(&(sharing as u32).to_le_bytes()).unwrap();
+    f.write_all(&[0u8; 16]).unwrap();
 
     for (rank_idx, &(orig_idx, equity, _)) in entries.iter().enumerate() {
         let hand = &canonical[orig_idx];
@@ -700,11 +1790,17 @@ fn run_precompute(args: &[String]) {
     eprintln!("  heroes_processed:          {}", num_hands);
     eprintln!("  boards_processed_total:    {}", boards_total);
     eprintln!("  boards_per_hero_average:   {}", if num_hands > 0 { boards_total / num_hands as u64 } else { 0 });
-    eprintln!("  villain_samples_per_board: {}", villain_samples);
+    if exact_villains {
+        eprintln!("  villain_samples_per_board: exact ({})", BINOM[42][5]);
+    } else {
+        eprintln!("  villain_samples_per_board: {}", villain_samples);
+    }
     eprintln!("  villain_samples_total:     {}", showdowns_total);
     eprintln!("  total_showdown_evals:      {}", showdowns_total);
+    eprintln!("  opponents:                 {}", opponents);
     eprintln!("  seed:                      {}", seed);
     eprintln!("  crn_mode:                  {}", if crn_active { "ON" } else { "OFF" });
+    eprintln!("  flop_sampling:             {}", if stratified && !full_enum { "stratified" } else { "random" });
     if crn_active {
         eprintln!("  crn_scenarios_generated:   {}", scenarios.len());
     }
@@ -746,56 +1842,48 @@ fn run_baseline(args: &[String]) {
     let indices: Vec<usize> = (0..num_baseline).map(|i| i * step).collect();
     eprintln!("Generating baselines for {} hands with {} trials each", indices.len(), trials);
 
-    let num_threads = num_cpus();
-    let chunk_size = (indices.len() + num_threads - 1) / num_threads;
-
-    let results: Vec<Vec<([u8; 5], f64)>> = thread::scope(|s| {
-        let handles: Vec<_> = (0..num_threads)
-            .map(|t| {
-                let table_ref = &table;
-                let canonical_ref = &canonical;
-                let indices_ref = &indices;
-                s.spawn(move || {
-                    let start = t * chunk_size;
-                    let end = (start + chunk_size).min(indices_ref.len());
-                    let mut thread_results = Vec::new();
-                    let mut rng = Xorshift64::new((t as u64 + 1) * 2862933555777941757);
-
-                    for &hi in &indices_ref[start..end] {
-                        let hero = &canonical_ref[hi].0;
-                        let hero_2s = two_card_subsets(hero);
-                        let remaining: Vec<u8> = (0..52u8).filter(|c| !hero.contains(c)).collect();
-
-                        let mut wins = 0.0f64;
-                        let mut total = 0u32;
-
-                        for _ in 0..trials {
-                            let board = sample_villain(&remaining, &mut rng);
-                            let mut sorted_board = board;
-                            sorted_board.sort();
-                            let board_3s = three_card_subsets(&sorted_board);
-                            let hero_rank = eval_best(&hero_2s, &board_3s, table_ref);
-
-                            let villain_pool: Vec<u8> = remaining.iter()
-                                .copied().filter(|c| !board.contains(c)).collect();
-                            let villain = sample_villain(&villain_pool, &mut rng);
-                            let villain_2s = two_card_subsets(&villain);
-                            let villain_rank = eval_best(&villain_2s, &board_3s, table_ref);
-
-                            if hero_rank < villain_rank { wins += 1.0; }
-                            else if hero_rank == villain_rank { wins += 0.5; }
-                            total += 1;
-                        }
+    let num_threads = num_threads();
+    let chunk_size = indices.len().div_ceil(num_threads);
+
+    let thread_rngs = EngineRng::streams(2862933555777941757, num_threads, |t| (t as u64 + 1) * 2862933555777941757);
+
+    let results: Vec<Vec<([u8; 5], f64)>> = parallel_map(num_threads, |t| {
+        let mut rng = thread_rngs[t].clone();
+        let start = t * chunk_size;
+        let end = (start + chunk_size).min(indices.len());
+        let mut thread_results = Vec::new();
+
+        for &hi in &indices[start..end] {
+            let hero = &canonical[hi].0;
+            let hero_2s = two_card_subsets(hero);
+            let remaining: Vec<u8> = (0..52u8).filter(|c| !hero.contains(c)).collect();
+
+            let mut wins = 0.0f64;
+            let mut total = 0u32;
+
+            for _ in 0..trials {
+                let board = sample_villain(&remaining, &mut rng);
+                let mut sorted_board = board;
+                sorted_board.sort();
+                let board_3s = three_card_subsets(&sorted_board);
+                let hero_rank = eval_best(&hero_2s, &board_3s, &table);
+
+                let villain_pool: Vec<u8> = remaining.iter()
+                    .copied().filter(|c| !board.contains(c)).collect();
+                let villain = sample_villain(&villain_pool, &mut rng);
+                let villain_2s = two_card_subsets(&villain);
+                let villain_rank = eval_best(&villain_2s, &board_3s, &table);
+
+                if hero_rank < villain_rank { wins += 1.0; }
+                else if hero_rank == villain_rank { wins += 0.5; }
+                total += 1;
+            }
 
-                        let equity = wins / total as f64;
-                        thread_results.push((*hero, equity));
-                        eprintln!("  Baseline {}: equity={:.4}%", hi, equity * 100.0);
-                    }
-                    thread_results
-                })
-            })
-            .collect();
-        handles.into_iter().map(|h| h.join().unwrap()).collect()
+            let equity = wins / total as f64;
+            thread_results.push((*hero, equity));
+            eprintln!("  Baseline {}: equity={:.4}%", hi, equity * 100.0);
+        }
+        thread_results
     });
 
     let all_results: Vec<([u8; 5], f64)> = results.into_iter().flatten().collect();
@@ -810,6 +1898,9 @@ fn run_baseline(args: &[String]) {
 }
 
 fn run_validate(args: &[String]) {
+    if args.iter().any(|a| a == "--statistical") {
+        return run_validate_statistical(args);
+    }
     let bin_path = parse_flag(args, "--bin").unwrap_or_else(|| "plo5_rankings_prod.bin".into());
     let baseline_path = parse_flag(args, "--baseline").unwrap_or_else(|| "baseline.json".into());
 
@@ -868,6 +1959,522 @@ fn run_validate(args: &[String]) {
     println!("  Status:     {}", if max_err <= 0.10 { "✓ PASS" } else { "✗ FAIL" });
 }
 
+/// Standard normal CDF (Abramowitz & Stegun 7.1.26, |error| < 1.5e-7).
+fn normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t * (0.254829592 + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erfc = poly * (-x * x).exp();
+    if z >= 0.0 { 1.0 - 0.5 * erfc } else { 0.5 * erfc }
+}
+
+/// Inverse of `normal_cdf` by bisection; plenty for critical values.
+fn normal_quantile(p: f64) -> f64 {
+    let (mut lo, mut hi) = (-40.0f64, 40.0f64);
+    for _ in 0..200 {
+        let mid = 0.5 * (lo + hi);
+        if normal_cdf(mid) < p { lo = mid; } else { hi = mid; }
+    }
+    0.5 * (lo + hi)
+}
+
+/// Upper-tail p-value of a chi-square statistic with `k` degrees of
+/// freedom, by the Wilson-Hilferty normal approximation.
+fn chi_square_p(x: f64, k: usize) -> f64 {
+    let k = k.max(1) as f64;
+    let c = 2.0 / (9.0 * k);
+    1.0 - normal_cdf(((x / k).cbrt() - (1.0 - c)) / c.sqrt())
+}
+
+/// Villains dealt per reference board in `validate --statistical`; two or
+/// more are needed to split the variance into board and villain parts.
+const VALIDATE_VILLAINS_PER_BOARD: u32 = 4;
+
+/// Hero equity from `boards` random runouts with several villains each, and
+/// the variance of one board's mean split into its parts: `between_var` from
+/// the runout itself and `within_var` from one villain deal on a fixed board.
+struct ClusteredEquity {
+    mean: f64,
+    boards: u32,
+    board_var: f64,
+    between_var: f64,
+    within_var: f64,
+}
+
+fn clustered_hero_equity<R: RandomSource>(hero: &[u8; 5], boards: u32, opponents: u32, rng: &mut R,
+                                          table: &[u16]) -> ClusteredEquity {
+    let m = VALIDATE_VILLAINS_PER_BOARD as usize;
+    let hero_2s = two_card_subsets(hero);
+    let remaining: Vec<u8> = (0..52u8).filter(|c| !hero.contains(c)).collect();
+    let mut board_means: Vec<f64> = Vec::with_capacity(boards as usize);
+    let mut within_sum = 0.0f64;
+    let mut shares = vec![0.0f64; m];
+    for _ in 0..boards {
+        let mut board = sample_villain(&remaining, rng);
+        board.sort();
+        let board_3s = three_card_subsets(&board);
+        let hero_rank = eval_best(&hero_2s, &board_3s, table);
+        let pool: Vec<u8> = remaining.iter().copied().filter(|c| !board.contains(c)).collect();
+        for share in shares.iter_mut() {
+            *share = multiway_share(hero_rank, &pool, opponents, rng,
+                |villain| eval_best(&two_card_subsets(villain), &board_3s, table));
+        }
+        let (mean, sd) = mean_sd(&shares);
+        board_means.push(mean);
+        within_sum += sd * sd;
+    }
+    let (mean, sd) = mean_sd(&board_means);
+    let within_var = within_sum / boards as f64;
+    ClusteredEquity {
+        mean,
+        boards,
+        board_var: sd * sd,
+        between_var: (sd * sd - within_var / m as f64).max(0.0),
+        within_var,
+    }
+}
+
+/// `validate --statistical`: checks the whole rankings file instead of
+/// fixed baselines. Hands are drawn at random from equal rank bands, each
+/// gets a fresh high-trial MC estimate, and the difference is scored as a
+/// z-value against the combined standard error of the file and the MC.
+/// The file passes when no |z| exceeds the Bonferroni limit for the sample
+/// and the chi-square of all z-values is not significant, both at `--alpha`.
+/// Both treat the z-values as independent, which only holds for files whose
+/// hands drew their own boards (`precompute --crn off` or `--boards full`).
+/// With shared CRN boards every hand carries the same board-sampling error,
+/// so those files are refused rather than scored.
+fn run_validate_statistical(args: &[String]) {
+    let bin_path = parse_flag(args, "--bin").unwrap_or_else(|| "plo5_rankings_prod.bin".into());
+    let hands: usize = parse_flag(args, "--hands")
+        .and_then(|s| s.parse().ok()).unwrap_or(200);
+    let trials: u32 = parse_flag(args, "--trials")
+        .and_then(|s| s.parse().ok()).unwrap_or(200_000);
+    let num_bands: usize = parse_flag(args, "--bands")
+        .and_then(|s| s.parse().ok()).unwrap_or(10).max(1);
+    let alpha: f64 = parse_flag(args, "--alpha")
+        .and_then(|s| s.parse().ok()).filter(|&a: &f64| a > 0.0 && a < 1.0).unwrap_or(0.01);
+    let seed: u64 = parse_flag(args, "--seed")
+        .and_then(|s| s.parse().ok()).unwrap_or(12345);
+    let json_output = args.iter().any(|a| a == "--json");
+
+    let bin_data = fs::read(&bin_path).unwrap_or_else(|e| fail(json_output, format!("Cannot read '{}': {}", bin_path, e)));
+    if bin_data.len() < 64 || &bin_data[0..4] != b"PLO5" {
+        fail(json_output, format!("'{}' is not a PLO5 rankings binary", bin_path));
+    }
+    let num_hands = u32::from_le_bytes(bin_data[8..12].try_into().unwrap()) as usize;
+    if bin_data.len() < 64 + num_hands * 20 || num_hands == 0 {
+        fail(json_output, format!("'{}' is truncated", bin_path));
+    }
+    match bin_board_sharing(&bin_data) {
+        BoardSharing::Independent => {}
+        BoardSharing::Shared => fail(json_output, format!(
+            "'{}' shares its CRN boards across hands, so its errors are correlated and the statistical check does not apply. Validate a file built with --crn off or --boards full.",
+            bin_path)),
+        BoardSharing::Unknown => fail(json_output, format!(
+            "'{}' does not record whether its boards were shared across hands (CRN was the default). Rebuild it with --crn off or --boards full to validate it statistically.",
+            bin_path)),
+    }
+    let v_samples = u32::from_le_bytes(bin_data[16..20].try_into().unwrap());
+    let avg_samples = u32::from_le_bytes(bin_data[20..24].try_into().unwrap());
+    let opponents = bin_opponents(&bin_data);
+    let record = |i: usize| -> ([u8; 5], f64) {
+        let off = 64 + i * 20;
+        (bin_data[off..off + 5].try_into().unwrap(),
+         f32::from_le_bytes(bin_data[off + 6..off + 10].try_into().unwrap()) as f64)
+    };
+
+    // Records are stored best first, so equal record ranges are equal
+    // percentile bands of canonical hands.
+    let mut rng = EngineRng::seeded(mix_seed(seed));
+    let mut picks: Vec<(usize, usize)> = Vec::with_capacity(hands);
+    for band in 0..num_bands {
+        let start = band * num_hands / num_bands;
+        let end = ((band + 1) * num_hands / num_bands).max(start + 1).min(num_hands);
+        let quota = (hands * (band + 1) / num_bands - hands * band / num_bands).min(end - start);
+        let mut chosen: HashSet<usize> = HashSet::new();
+        while chosen.len() < quota {
+            chosen.insert(start + rng.gen_range(end - start));
+        }
+        let mut chosen: Vec<usize> = chosen.into_iter().collect();
+        chosen.sort();
+        picks.extend(chosen.into_iter().map(|i| (band, i)));
+    }
+
+    if !json_output {
+        eprintln!("Statistical validation of {} ({} hands, {} opponent(s))", bin_path, num_hands, opponents);
+        eprintln!("  {} hands in {} rank bands, {} MC trials each", picks.len(), num_bands, trials);
+    }
+    let t0 = Instant::now();
+    let table = init_eval_table();
+    let hand_rngs = EngineRng::streams(seed, picks.len(), |k| mix_seed(seed.wrapping_add(k as u64 + 1)));
+    let mc: Vec<ClusteredEquity> = parallel_map(picks.len(), |k| {
+        let (hero, _) = record(picks[k].1);
+        let mut rng = hand_rngs[k].clone();
+        clustered_hero_equity(&hero, (trials / VALIDATE_VILLAINS_PER_BOARD).max(2), opponents, &mut rng, &table)
+    });
+
+    // The file averages `boards` per-board means of V villains each (exact
+    // counting is V = infinity), so its variance follows from the same two
+    // components the reference measures. The header's board count is taken
+    // before CRN skips boards that touch the hero, so the boards a hand was
+    // really averaged over come from its samples (boards in exact mode).
+    let file_boards = if v_samples == 0 {
+        avg_samples as f64
+    } else {
+        avg_samples as f64 / v_samples as f64
+    }.max(1.0);
+    let mut z_values: Vec<f64> = Vec::with_capacity(picks.len());
+    let mut errors: Vec<f64> = Vec::with_capacity(picks.len());
+    for (k, &(_, i)) in picks.iter().enumerate() {
+        let (_, file_eq) = record(i);
+        let est = &mc[k];
+        let villain_var = if v_samples == 0 { 0.0 } else { est.within_var / v_samples as f64 };
+        let file_var = (est.between_var + villain_var) / file_boards;
+        let mc_var = est.board_var / est.boards as f64;
+        // A floor keeps hands with (near) zero observed spread from blowing up z.
+        let se = (file_var + mc_var).sqrt().max(1e-6);
+        errors.push(file_eq - est.mean);
+        z_values.push((file_eq - est.mean) / se);
+    }
+    let n = z_values.len();
+    let chi2: f64 = z_values.iter().map(|z| z * z).sum();
+    let chi2_p = chi_square_p(chi2, n);
+    let z_limit = normal_quantile(1.0 - alpha / (2.0 * n.max(1) as f64));
+    let max_z = z_values.iter().fold(0.0f64, |m, z| m.max(z.abs()));
+    let rms_z = (chi2 / n.max(1) as f64).sqrt();
+
+    // Rank index: every combo once (the flag), in non-increasing order of its
+    // class's equity in the binary (inversions and the largest one).
+    let rank_file = parse_flag(args, "--rank-file")
+        .unwrap_or_else(|| multiway_path("public/rank_index_all_2598960.u32", opponents));
+    let index_check: Option<(bool, u64, f64)> = fs::read(&rank_file).ok().map(|data| {
+        if data.len() != BINOM[52][5] as usize * 4 {
+            return (false, 0, 0.0);
+        }
+        let canon_index = CanonicalIndex::build();
+        let mut class_equity = vec![f64::NAN; canon_index.len()];
+        for i in 0..num_hands {
+            let (cards, eq) = record(i);
+            class_equity[canon_index.id(&cards)] = eq;
+        }
+        let mut seen = vec![false; BINOM[52][5] as usize];
+        let mut permutation = true;
+        let mut inversions = 0u64;
+        let mut worst = 0.0f64;
+        let mut prev = f64::INFINITY;
+        for chunk in data.chunks_exact(4) {
+            let idx = u32::from_le_bytes(chunk.try_into().unwrap()) as usize;
+            if idx >= seen.len() || seen[idx] {
+                permutation = false;
+                break;
+            }
+            seen[idx] = true;
+            let eq = class_equity[canon_index.ids[idx] as usize];
+            if eq > prev {
+                inversions += 1;
+                worst = worst.max(eq - prev);
+            }
+            prev = eq;
+        }
+        (permutation, inversions, worst)
+    });
+
+    struct Band { count: usize, mean_abs: f64, max_abs: f64, rms_z: f64, max_z: f64 }
+    let bands: Vec<Band> = (0..num_bands).map(|b| {
+        let members: Vec<usize> = (0..n).filter(|&k| picks[k].0 == b).collect();
+        let m = members.len().max(1) as f64;
+        Band {
+            count: members.len(),
+            mean_abs: members.iter().map(|&k| errors[k].abs()).sum::<f64>() / m,
+            max_abs: members.iter().fold(0.0, |x, &k| x.max(errors[k].abs())),
+            rms_z: (members.iter().map(|&k| z_values[k] * z_values[k]).sum::<f64>() / m).sqrt(),
+            max_z: members.iter().fold(0.0, |x, &k| x.max(z_values[k].abs())),
+        }
+    }).collect();
+
+    let stats_pass = max_z <= z_limit && chi2_p >= alpha;
+    let pass = stats_pass && index_check.is_none_or(|(permutation, inversions, _)| permutation && inversions == 0);
+
+    if json_output {
+        let bands_json: Vec<String> = bands.iter().enumerate().map(|(b, band)| {
+            format!("{{\"fromPct\":{:.2},\"toPct\":{:.2},\"hands\":{},\"meanAbsError\":{:.6},\"maxAbsError\":{:.6},\"rmsZ\":{:.4},\"maxZ\":{:.4}}}",
+                b as f64 * 100.0 / num_bands as f64, (b + 1) as f64 * 100.0 / num_bands as f64,
+                band.count, band.mean_abs, band.max_abs, band.rms_z, band.max_z)
+        }).collect();
+        let index_json = match index_check {
+            Some((permutation, inversions, worst)) => format!("{{\"file\":\"{}\",\"ok\":{},\"permutation\":{},\"inversions\":{},\"maxInversion\":{:.6}}}",
+                rank_file, permutation && inversions == 0, permutation, inversions, worst),
+            None => "null".into(),
+        };
+        println!("{{\"ok\":true,\"hands\":{},\"trials\":{},\"fileSamples\":{},\"opponents\":{},\"alpha\":{},\"zLimit\":{:.4},\"maxZ\":{:.4},\"rmsZ\":{:.4},\"chi2\":{:.3},\"chi2P\":{:.6},\"bands\":[{}],\"rankIndex\":{},\"pass\":{}}}",
+            n, trials, avg_samples, opponents, alpha, z_limit, max_z, rms_z, chi2, chi2_p,
+            bands_json.join(","), index_json, pass);
+        return;
+    }
+
+    println!();
+    println!("╔══════════════════════════════════════════════╗");
+    println!("║      Statistical Validation Report            ║");
+    println!("╚══════════════════════════════════════════════╝");
+    println!("  Engine:     {} hands, V={}, avg {} samples/hand",
+        num_hands, if v_samples == 0 { "exact".to_string() } else { v_samples.to_string() }, avg_samples);
+    println!("  Reference:  {} hands × {} MC trials ({:.1}s)", n, trials, t0.elapsed().as_secs_f64());
+    println!();
+    println!("  Band          Hands   Mean |err|   Max |err|   RMS z   Max |z|");
+    for (b, band) in bands.iter().enumerate() {
+        println!("  {:>5.1}-{:<5.1}%  {:>5}   {:>8.4}%   {:>8.4}%   {:>5.2}   {:>7.2}",
+            b as f64 * 100.0 / num_bands as f64, (b + 1) as f64 * 100.0 / num_bands as f64,
+            band.count, band.mean_abs * 100.0, band.max_abs * 100.0, band.rms_z, band.max_z);
+    }
+    println!();
+    println!("  RMS z:      {:.3} (≈1 when errors match the expected sampling noise)", rms_z);
+    println!("  Max |z|:    {:.3} (limit {:.3}, Bonferroni at α={})", max_z, z_limit, alpha);
+    println!("  Chi-square: {:.1} on {} df, p = {:.4}", chi2, n, chi2_p);
+    match index_check {
+        Some((false, _, _)) => println!("  Rank index: {} is not a permutation of all 2,598,960 hands", rank_file),
+        Some((true, 0, _)) => println!("  Rank index: {} agrees with the binary", rank_file),
+        Some((true, inversions, worst)) => println!("  Rank index: {} has {} order inversions (worst {:.4}%)",
+            rank_file, inversions, worst * 100.0),
+        None => println!("  Rank index: {} not found, skipped", rank_file),
+    }
+    println!("  Status:     {}", if pass { "✓ PASS" } else { "✗ FAIL" });
+}
+
+/// A rankings file reduced to one equity per canonical hand, indexed by
+/// `CanonicalIndex` id. `.f32` files hold every combo, so each class gets
+/// the mean of its combos.
+struct Rankings {
+    kind: &'static str,
+    equity: Vec<f64>,
+    boards: u32,
+    villain_samples: u32,
+    /// 0 for a `.f32` file without a sidecar.
+    opponents: u32,
+}
+
+fn load_rankings(path: &str, index: &CanonicalIndex) -> Result<Rankings, String> {
+    let data = fs::read(path).map_err(|e| format!("Cannot read '{}': {}", path, e))?;
+    if data.len() >= 64 && &data[0..4] == b"PLO5" {
+        let num_hands = u32::from_le_bytes(data[8..12].try_into().unwrap()) as usize;
+        if data.len() < 64 + num_hands * 20 {
+            return Err(format!("'{}' is truncated: {} bytes for {} hands", path, data.len(), num_hands));
+        }
+        let mut equity = vec![f64::NAN; index.len()];
+        for i in 0..num_hands {
+            let off = 64 + i * 20;
+            let cards: [u8; 5] = data[off..off + 5].try_into().unwrap();
+            equity[index.id(&cards)] = f32::from_le_bytes(data[off + 6..off + 10].try_into().unwrap()) as f64;
+        }
+        let missing = equity.iter().filter(|e| e.is_nan()).count();
+        if missing > 0 {
+            return Err(format!("'{}' is missing {} of {} canonical hands", path, missing, index.len()));
+        }
+        return Ok(Rankings {
+            kind: "bin",
+            equity,
+            boards: u32::from_le_bytes(data[12..16].try_into().unwrap()),
+            villain_samples: u32::from_le_bytes(data[16..20].try_into().unwrap()),
+            opponents: bin_opponents(&data),
+        });
+    }
+    if data.len() == BINOM[52][5] as usize * 4 {
+        let mut equity = vec![0.0f64; index.len()];
+        for (chunk, &id) in data.chunks_exact(4).zip(&index.ids) {
+            equity[id as usize] += f32::from_le_bytes(chunk.try_into().unwrap()) as f64;
+        }
+        for (eq, &(_, combos)) in equity.iter_mut().zip(&index.hands) {
+            *eq /= combos as f64;
+        }
+        let opponents = read_opponents_meta(path).unwrap_or(0);
+        return Ok(Rankings { kind: "f32", equity, boards: 0, villain_samples: 0, opponents });
+    }
+    Err(format!("'{}' is neither a PLO5 rankings binary nor a 2,598,960-hand .f32 equity file", path))
+}
+
+/// Fraction of all combos at which `diff_rankings` reports range boundaries.
+const DIFF_PERCENTILES: [f64; 4] = [5.0, 10.0, 20.0, 50.0];
+
+/// How the top `pct`% of combos differs between the two rankings.
+struct BoundaryShift {
+    pct: f64,
+    equity_a: f64,
+    equity_b: f64,
+    /// Combos in the second file's range that the first file's range lacks.
+    entering: u64,
+    /// Combos in the first file's range that the second file's range lacks.
+    leaving: u64,
+}
+
+struct RankingsDiff {
+    spearman: f64,
+    max_delta: f64,
+    mean_delta: f64,
+    rms_delta: f64,
+    p95_delta: f64,
+    /// Canonical rank (0 = best) of each hand in the first and second file.
+    rank_a: Vec<u32>,
+    rank_b: Vec<u32>,
+    /// Ids of the hands that moved, by decreasing absolute rank move.
+    moves: Vec<u32>,
+    boundaries: Vec<BoundaryShift>,
+}
+
+/// Compares two per-class equity vectors; `combos` weights each class for
+/// the percentile boundaries, as the UI ranges count combos, not classes.
+fn diff_rankings(a: &[f64], b: &[f64], combos: &[u32]) -> RankingsDiff {
+    let order_a = rank_order(a);
+    let order_b = rank_order(b);
+    let positions = |order: &[u32]| {
+        let mut rank = vec![0u32; order.len()];
+        for (pos, &id) in order.iter().enumerate() {
+            rank[id as usize] = pos as u32;
+        }
+        rank
+    };
+    let rank_a = positions(&order_a);
+    let rank_b = positions(&order_b);
+
+    let mut deltas: Vec<f64> = a.iter().zip(b).map(|(x, y)| (x - y).abs()).collect();
+    let n = deltas.len().max(1) as f64;
+    let mean_delta = deltas.iter().sum::<f64>() / n;
+    let rms_delta = (deltas.iter().map(|d| d * d).sum::<f64>() / n).sqrt();
+    deltas.sort_by(|x, y| x.partial_cmp(y).unwrap_or(std::cmp::Ordering::Equal));
+    let max_delta = deltas.last().copied().unwrap_or(0.0);
+    let p95_delta = deltas.get(((n * 0.95) as usize).min(deltas.len().saturating_sub(1))).copied().unwrap_or(0.0);
+
+    let mut moves: Vec<u32> = (0..a.len() as u32)
+        .filter(|&id| rank_a[id as usize] != rank_b[id as usize])
+        .collect();
+    moves.sort_by_key(|&id| std::cmp::Reverse(rank_a[id as usize].abs_diff(rank_b[id as usize])));
+
+    let total: u64 = combos.iter().map(|&c| c as u64).sum();
+    let top = |order: &[u32], pct: f64| -> usize {
+        let target = (pct / 100.0 * total as f64).ceil() as u64;
+        let mut cumulative = 0u64;
+        order.iter().position(|&id| {
+            cumulative += combos[id as usize] as u64;
+            cumulative >= target
+        }).map_or(order.len(), |pos| pos + 1)
+    };
+    let boundaries = DIFF_PERCENTILES.iter().map(|&pct| {
+        let (len_a, len_b) = (top(&order_a, pct), top(&order_b, pct));
+        let outside = |rank: &[u32], len: usize, ids: &[u32]| -> u64 {
+            ids.iter().filter(|&&id| rank[id as usize] as usize >= len).map(|&id| combos[id as usize] as u64).sum()
+        };
+        BoundaryShift {
+            pct,
+            equity_a: a[order_a[len_a - 1] as usize],
+            equity_b: b[order_b[len_b - 1] as usize],
+            entering: outside(&rank_a, len_a, &order_b[..len_b]),
+            leaving: outside(&rank_b, len_b, &order_a[..len_a]),
+        }
+    }).collect();
+
+    RankingsDiff {
+        spearman: spearman(&order_a, &order_b),
+        max_delta,
+        mean_delta,
+        rms_delta,
+        p95_delta,
+        rank_a,
+        rank_b,
+        moves,
+        boundaries,
+    }
+}
+
+fn run_diff_rankings(args: &[String]) {
+    let json_output = args.iter().any(|a| a == "--json");
+    let (path_a, path_b) = match (parse_flag(args, "--a"), parse_flag(args, "--b")) {
+        (Some(a), Some(b)) => (a, b),
+        _ => fail(json_output, "Usage: plo5_ranker diff_rankings --a <file> --b <file> [--top N] [--json]".into()),
+    };
+    let top_moves: usize = parse_flag(args, "--top")
+        .and_then(|s| s.parse().ok()).unwrap_or(20);
+
+    let index = CanonicalIndex::build();
+    let a = load_rankings(&path_a, &index).unwrap_or_else(|msg| fail(json_output, msg));
+    let b = load_rankings(&path_b, &index).unwrap_or_else(|msg| fail(json_output, msg));
+    let combos: Vec<u32> = index.hands.iter().map(|&(_, c)| c).collect();
+    let diff = diff_rankings(&a.equity, &b.equity, &combos);
+    let moves = &diff.moves[..top_moves.min(diff.moves.len())];
+    let hand_name = |id: u32| index.hands[id as usize].0.iter().map(|&c| card_name(c)).collect::<String>();
+    // Two runs that differ only in seed disagree by sqrt(2) times the
+    // per-file error, which is what `validate` estimates against baselines.
+    let seed_error = diff.rms_delta / std::f64::consts::SQRT_2;
+    let seed_p95 = diff.p95_delta / std::f64::consts::SQRT_2;
+    let same_config = a.kind == b.kind && a.boards == b.boards
+        && a.villain_samples == b.villain_samples && a.opponents == b.opponents;
+    // Rankings against different opponent counts order hands differently by
+    // design, so their Δ says nothing about sampling error.
+    let opponents_differ = a.opponents != 0 && b.opponents != 0 && a.opponents != b.opponents;
+
+    if json_output {
+        let moves_json: Vec<String> = moves.iter().map(|&id| {
+            let i = id as usize;
+            format!("{{\"hand\":\"{}\",\"rankA\":{},\"rankB\":{},\"equityA\":{:.6},\"equityB\":{:.6}}}",
+                hand_name(id), diff.rank_a[i] + 1, diff.rank_b[i] + 1, a.equity[i], b.equity[i])
+        }).collect();
+        let boundaries_json: Vec<String> = diff.boundaries.iter().map(|s| {
+            format!("{{\"pct\":{},\"equityA\":{:.6},\"equityB\":{:.6},\"entering\":{},\"leaving\":{}}}",
+                s.pct, s.equity_a, s.equity_b, s.entering, s.leaving)
+        }).collect();
+        println!("{{\"ok\":true,\"hands\":{},\"spearman\":{:.8},\"maxDelta\":{:.6},\"meanDelta\":{:.6},\"rmsDelta\":{:.6},\"p95Delta\":{:.6},\"seedStdError\":{:.6},\"seedP95Error\":{:.6},\"sameConfig\":{},\"opponentsA\":{},\"opponentsB\":{},\"opponentsDiffer\":{},\"moves\":[{}],\"boundaries\":[{}]}}",
+            index.len(), diff.spearman, diff.max_delta, diff.mean_delta, diff.rms_delta, diff.p95_delta,
+            seed_error, seed_p95, same_config, a.opponents, b.opponents, opponents_differ,
+            moves_json.join(","), boundaries_json.join(","));
+        return;
+    }
+
+    eprintln!("╔══════════════════════════════════════════════╗");
+    eprintln!("║            Rankings Comparison                ║");
+    eprintln!("╚══════════════════════════════════════════════╝");
+    for (label, path, r) in [("A", &path_a, &a), ("B", &path_b, &b)] {
+        if r.kind == "bin" {
+            eprintln!("  {}: {} ({} boards/hero, V={}, {} opponent(s))", label, path, r.boards,
+                if r.villain_samples == 0 { "exact".to_string() } else { r.villain_samples.to_string() }, r.opponents);
+        } else {
+            let opponents = if r.opponents == 0 { "opponents unknown".to_string() } else { format!("{} opponent(s)", r.opponents) };
+            eprintln!("  {}: {} (all-combo .f32, averaged per canonical hand, {})", label, path, opponents);
+        }
+    }
+    if opponents_differ {
+        eprintln!("  WARNING: A is against {} opponent(s) and B against {}; the rankings are not comparable.",
+            a.opponents, b.opponents);
+    }
+    eprintln!("  Canonical hands:   {}", index.len());
+    eprintln!();
+    eprintln!("  Spearman rho:      {:.6}", diff.spearman);
+    eprintln!("  Max |Δ equity|:    {:.4}%", diff.max_delta * 100.0);
+    eprintln!("  Mean |Δ equity|:   {:.4}%", diff.mean_delta * 100.0);
+    eprintln!("  RMS Δ equity:      {:.4}%", diff.rms_delta * 100.0);
+    eprintln!("  P95 |Δ equity|:    {:.4}%", diff.p95_delta * 100.0);
+    eprintln!();
+    if same_config {
+        eprintln!("  Seed consistency (same settings, so Δ is sampling noise from both files):");
+    } else {
+        eprintln!("  Seed consistency (settings differ, so Δ also includes real differences):");
+    }
+    eprintln!("    Per-file error:  {:.4}% (RMS Δ / √2)", seed_error * 100.0);
+    eprintln!("    Per-file P95:    {:.4}%", seed_p95 * 100.0);
+    eprintln!();
+    eprintln!("  Percentile boundaries (by combos):");
+    eprintln!("    Top     Equity A   Equity B    Entering     Leaving");
+    for s in &diff.boundaries {
+        eprintln!("    {:>4.0}%  {:>8.3}%  {:>8.3}%  {:>10}  {:>10}",
+            s.pct, s.equity_a * 100.0, s.equity_b * 100.0, s.entering, s.leaving);
+    }
+    eprintln!();
+    eprintln!("  Largest rank moves:");
+    eprintln!("    Hand              Rank A    Rank B     Equity A   Equity B");
+    for &id in moves {
+        let i = id as usize;
+        eprintln!("    {:<14}  {:>8}  {:>8}  {:>9.3}%  {:>8.3}%", hand_name(id),
+            diff.rank_a[i] + 1, diff.rank_b[i] + 1, a.equity[i] * 100.0, b.equity[i] * 100.0);
+    }
+}
+
 fn parse_baselines(json: &str) -> Vec<([u8; 5], f64)> {
     let mut results = Vec::new();
     let mut pos = 0;
@@ -883,7 +2490,7 @@ fn parse_baselines(json: &str) -> Vec<([u8; 5], f64)> {
                     let eq_start = start + end_bracket;
                     if let Some(eq_pos) = json[eq_start..].find("\"equity\":") {
                         let val_start = eq_start + eq_pos + 9;
-                        let val_end = json[val_start..].find(|c: char| c == ',' || c == '}')
+                        let val_end = json[val_start..].find([',', '}'])
                             .unwrap_or(json.len() - val_start);
                         let eq: f64 = json[val_start..val_start + val_end].trim().parse().unwrap_or(0.0);
                         results.push((cards, eq));
@@ -896,6 +2503,86 @@ fn parse_baselines(json: &str) -> Vec<([u8; 5], f64)> {
     results
 }
 
+/// Selected in `main` by `--lenient-cards`: card strings go through
+/// `parse_cards_vec`, which skips anything it does not recognize.
+static LENIENT_CARDS: AtomicBool = AtomicBool::new(false);
+
+/// Card string parser used by every subcommand: strict unless
+/// `--lenient-cards` was given.
+fn parse_cards(s: &str) -> Result<Vec<u8>, String> {
+    if LENIENT_CARDS.load(Ordering::Relaxed) {
+        Ok(parse_cards_vec(s))
+    } else {
+        parse_cards_strict(s)
+    }
+}
+
+fn card_suit(ch: char) -> Option<u8> {
+    match ch {
+        'c' | 'C' | '♣' | '♧' => Some(0),
+        'd' | 'D' | '♦' | '♢' => Some(1),
+        'h' | 'H' | '♥' | '♡' => Some(2),
+        's' | 'S' | '♠' | '♤' => Some(3),
+        _ => None,
+    }
+}
+
+/// `ch` quoted for an error message that may end up inside JSON.
+fn quoted_char(ch: char) -> String {
+    if ch.is_ascii() { format!("'{}'", ch.escape_default()) } else { format!("'{}'", ch) }
+}
+
+/// Strict card parser: ranks 2-9, T or 10, J, Q, K, A and suits c, d, h, s
+/// in either case or as ♣♦♥♠, optionally separated by spaces, commas,
+/// dashes, slashes or brackets. Anything else, a rank without a suit, or a
+/// repeated card is an error naming the 1-based character position.
+fn parse_cards_strict(s: &str) -> Result<Vec<u8>, String> {
+    let chars: Vec<char> = s.chars().collect();
+    let mut cards: Vec<u8> = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let ch = chars[i];
+        if ch.is_whitespace() || ",;-/|[](){}".contains(ch) {
+            i += 1;
+            continue;
+        }
+        let start = i + 1;
+        let rank = match ch.to_ascii_uppercase() {
+            '1' if chars.get(i + 1) == Some(&'0') => {
+                i += 1;
+                8
+            }
+            r @ '2'..='9' => r as u8 - b'2',
+            'T' => 8,
+            'J' => 9,
+            'Q' => 10,
+            'K' => 11,
+            'A' => 12,
+            _ => return Err(format!("Invalid rank {} at position {}", quoted_char(ch), start)),
+        };
+        i += 1;
+        let suit = match chars.get(i) {
+            Some(&c) => card_suit(c).ok_or_else(|| {
+                format!("Invalid suit {} at position {} (expected c, d, h, s or ♣♦♥♠)", quoted_char(c), i + 1)
+            })?,
+            None => return Err(format!("Missing suit after rank {} at position {}", quoted_char(ch), start)),
+        };
+        i += 1;
+        // Emoji presentation of the suit symbols (♠️).
+        if chars.get(i) == Some(&'\u{FE0F}') {
+            i += 1;
+        }
+        let card = suit * 13 + rank;
+        if cards.contains(&card) {
+            return Err(format!("Duplicate card {} at position {}", card_name(card), start));
+        }
+        cards.push(card);
+    }
+    Ok(cards)
+}
+
+/// Lenient parser kept for `--lenient-cards`: skips anything that is not a
+/// rank followed by a lowercase suit.
 fn parse_cards_vec(s: &str) -> Vec<u8> {
     let ranks = "23456789TJQKA";
     let suits = "cdhs";
@@ -904,13 +2591,18 @@ fn parse_cards_vec(s: &str) -> Vec<u8> {
     let mut i = 0;
     let bytes = s.as_bytes();
     while i < bytes.len() {
-        let r_ch = bytes[i] as char;
-        if let Some(r) = ranks.find(r_ch) {
-            if i + 1 < bytes.len() {
-                let s_ch = bytes[i + 1] as char;
+        // "10" is accepted as an alternative to 'T'.
+        let (rank, width) = if bytes[i..].starts_with(b"10") {
+            (ranks.find('T'), 2)
+        } else {
+            (ranks.find(bytes[i] as char), 1)
+        };
+        if let Some(r) = rank {
+            if i + width < bytes.len() {
+                let s_ch = bytes[i + width] as char;
                 if let Some(su) = suits.find(s_ch) {
                     cards.push((su as u8) * 13 + r as u8);
-                    i += 2;
+                    i += width + 1;
                     continue;
                 }
             }
@@ -920,18 +2612,18 @@ fn parse_cards_vec(s: &str) -> Vec<u8> {
     cards
 }
 
-fn parse_hand(s: &str) -> Option<[u8; 5]> {
-    let cards = parse_cards_vec(s);
+fn parse_hand(s: &str) -> Result<[u8; 5], String> {
+    let cards = parse_cards(s)?;
     if cards.len() == 5 {
         let mut arr = [cards[0], cards[1], cards[2], cards[3], cards[4]];
         arr.sort();
-        Some(arr)
+        Ok(arr)
     } else {
-        None
+        Err(format!("need exactly 5 cards, got {}", cards.len()))
     }
 }
 
-fn sample_n(pool: &[u8], n: usize, rng: &mut Xorshift64) -> Vec<u8> {
+fn sample_n<R: RandomSource>(pool: &[u8], n: usize, rng: &mut R) -> Vec<u8> {
     let plen = pool.len();
     let mut indices = Vec::with_capacity(n);
     for _ in 0..n {
@@ -947,27 +2639,36 @@ fn sample_n(pool: &[u8], n: usize, rng: &mut Xorshift64) -> Vec<u8> {
     indices.iter().map(|&i| pool[i]).collect()
 }
 
-fn run_accuracy(args: &[String]) {
-    let bin_path = parse_flag(args, "--bin").unwrap_or_else(|| "plo5_rankings_prod.bin".into());
+const DEFAULT_TEST_HANDS: [&str; 10] = [
+    "AcAdAhKsQs", "AcAdKcKdQh", "AcAdKhQhJh", "KcKdKhQsJs", "JcTc9c8c7c",
+    "AcKcQcJcTd", "2c3c4d5d7h", "AcAd2s3s4h", "TcTd9h8h7s", "6c6d5h4h3s",
+];
+
+/// Separates hands in a list flag. The card parser skips commas, spaces and
+/// most punctuation inside a hand, so lists need a character it rejects.
+const HAND_LIST_SEPARATOR: char = ':';
+
+/// Splits a hand list on `HAND_LIST_SEPARATOR`. Lists without one are read
+/// the old way, comma-separated, so existing scripts (and `--lenient-cards`
+/// runs) keep working.
+fn split_hand_list(value: &str) -> Vec<&str> {
+    let separator = if value.contains(HAND_LIST_SEPARATOR) { HAND_LIST_SEPARATOR } else { ',' };
+    value.split(separator).map(str::trim).collect()
+}
+
+fn parse_test_hands(args: &[String]) -> Vec<String> {
+    match parse_flag(args, "--test-hands") {
+        Some(hands_arg) => split_hand_list(&hands_arg).into_iter().map(String::from).collect(),
+        None => DEFAULT_TEST_HANDS.iter().map(|s| s.to_string()).collect(),
+    }
+}
+
+fn run_accuracy(args: &[String]) {
+    let bin_path = parse_flag(args, "--bin").unwrap_or_else(|| "plo5_rankings_prod.bin".into());
     let trials: u64 = parse_flag(args, "--trials")
         .and_then(|s| s.parse().ok()).unwrap_or(2_000_000);
 
-    let test_hands_str: Vec<String> = if let Some(hands_arg) = parse_flag(args, "--test-hands") {
-        hands_arg.split(',').map(|s| s.trim().to_string()).collect()
-    } else {
-        vec![
-            "AcAdAhKsQs".into(),
-            "AcAdKcKdQh".into(),
-            "AcAdKhQhJh".into(),
-            "KcKdKhQsJs".into(),
-            "JcTc9c8c7c".into(),
-            "AcKcQcJcTd".into(),
-            "2c3c4d5d7h".into(),
-            "AcAd2s3s4h".into(),
-            "TcTd9h8h7s".into(),
-            "6c6d5h4h3s".into(),
-        ]
-    };
+    let test_hands_str = parse_test_hands(args);
 
     eprintln!("╔══════════════════════════════════════════════╗");
     eprintln!("║       PLO5 Accuracy Benchmark                ║");
@@ -1003,15 +2704,13 @@ fn run_accuracy(args: &[String]) {
 
     let mut test_hands: Vec<([u8; 5], String)> = Vec::new();
     for s in &test_hands_str {
-        if let Some(h) = parse_hand(s.as_str()) {
-            let can = canonicalize(&h);
-            test_hands.push((can, s.clone()));
-        } else {
-            eprintln!("  WARNING: Could not parse hand '{}'", s);
+        match parse_hand(s.as_str()) {
+            Ok(h) => test_hands.push((canonicalize(&h), s.clone())),
+            Err(e) => eprintln!("  WARNING: Could not parse hand '{}' ({})", s, e),
         }
     }
 
-    let num_threads = num_cpus();
+    let num_threads = num_threads();
 
     eprintln!("  {:>3}  {:<14}  {:>10}  {:>10}  {:>8}", "#", "Hand", "Engine%", "MC_2M%", "Delta%");
     eprintln!("  ───  ──────────────  ──────────  ──────────  ────────");
@@ -1023,45 +2722,36 @@ fn run_accuracy(args: &[String]) {
         let hero_2s = two_card_subsets(&hero);
         let remaining: Vec<u8> = (0..52u8).filter(|c| !hero.contains(c)).collect();
 
-        let chunk = (trials as usize + num_threads - 1) / num_threads;
-        let mc_equity: f64 = thread::scope(|s| {
-            let handles: Vec<_> = (0..num_threads).map(|t| {
-                let table_ref = &table;
-                let hero_2s_ref = &hero_2s;
-                let remaining_ref = &remaining;
-                s.spawn(move || {
-                    let start = t * chunk;
-                    let end = ((t + 1) * chunk).min(trials as usize);
-                    let mut rng = Xorshift64::new(
-                        (t as u64 + 42 + idx as u64).wrapping_mul(6364136223846793005).wrapping_add(1)
-                    );
-                    let mut wins = 0.0f64;
-                    let mut total = 0u64;
-                    for _ in start..end {
-                        let board = sample_villain(remaining_ref, &mut rng);
-                        let mut sorted_board = board;
-                        sorted_board.sort();
-                        let board_3s = three_card_subsets(&sorted_board);
-                        let hero_rank = eval_best(hero_2s_ref, &board_3s, table_ref);
-
-                        let pool: Vec<u8> = remaining_ref.iter()
-                            .copied().filter(|c| !board.contains(c)).collect();
-                        let villain = sample_villain(&pool, &mut rng);
-                        let villain_2s = two_card_subsets(&villain);
-                        let villain_rank = eval_best(&villain_2s, &board_3s, table_ref);
-
-                        if hero_rank < villain_rank { wins += 1.0; }
-                        else if hero_rank == villain_rank { wins += 0.5; }
-                        total += 1;
-                    }
-                    (wins, total)
-                })
-            }).collect();
-            let (total_wins, total_count): (f64, u64) = handles.into_iter()
-                .map(|h| h.join().unwrap())
-                .fold((0.0, 0), |(w, c), (w2, c2)| (w + w2, c + c2));
-            total_wins / total_count as f64
+        let chunk = (trials as usize).div_ceil(num_threads);
+        let thread_rngs = EngineRng::streams(42 + idx as u64, num_threads, |t| {
+            (t as u64 + 42 + idx as u64).wrapping_mul(6364136223846793005).wrapping_add(1)
         });
+        let (total_wins, total_count) = parallel_map(num_threads, |t| {
+            let start = t * chunk;
+            let end = ((t + 1) * chunk).min(trials as usize);
+            let mut rng = thread_rngs[t].clone();
+            let mut wins = 0.0f64;
+            let mut total = 0u64;
+            for _ in start..end {
+                let board = sample_villain(&remaining, &mut rng);
+                let mut sorted_board = board;
+                sorted_board.sort();
+                let board_3s = three_card_subsets(&sorted_board);
+                let hero_rank = eval_best(&hero_2s, &board_3s, &table);
+
+                let pool: Vec<u8> = remaining.iter()
+                    .copied().filter(|c| !board.contains(c)).collect();
+                let villain = sample_villain(&pool, &mut rng);
+                let villain_2s = two_card_subsets(&villain);
+                let villain_rank = eval_best(&villain_2s, &board_3s, &table);
+
+                if hero_rank < villain_rank { wins += 1.0; }
+                else if hero_rank == villain_rank { wins += 0.5; }
+                total += 1;
+            }
+            (wins, total)
+        }).into_iter().fold((0.0f64, 0u64), |(w, c), (w2, c2)| (w + w2, c + c2));
+        let mc_equity = total_wins / total_count as f64;
 
         let engine_eq = engine_map.get(canonical_hand).copied().unwrap_or(f64::NAN);
         let delta = (engine_eq - mc_equity) * 100.0;
@@ -1078,6 +2768,356 @@ fn run_accuracy(args: &[String]) {
     eprintln!("  Total time:  {:.1}s", t0.elapsed().as_secs_f64());
 }
 
+/// Replicate mean and sample standard deviation.
+fn mean_sd(values: &[f64]) -> (f64, f64) {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let var = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / (n - 1.0).max(1.0);
+    (mean, var.sqrt())
+}
+
+fn run_variance(args: &[String]) {
+    let boards: u32 = parse_flag(args, "--boards")
+        .and_then(|s| s.parse().ok()).unwrap_or(500);
+    let villain_samples: u32 = parse_flag(args, "--villain-samples")
+        .and_then(|s| s.parse().ok()).unwrap_or(10);
+    let trials: usize = parse_flag(args, "--trials")
+        .and_then(|s| s.parse().ok()).unwrap_or(10_000);
+    let replicates: usize = parse_flag(args, "--replicates")
+        .and_then(|s| s.parse().ok()).unwrap_or(24).max(2);
+    let seed: u64 = parse_flag(args, "--seed")
+        .and_then(|s| s.parse().ok()).unwrap_or(12345);
+    let json_output = args.iter().any(|a| a == "--json");
+
+    let mut test_hands: Vec<([u8; 5], String)> = Vec::new();
+    for s in parse_test_hands(args) {
+        match parse_hand(&s) {
+            Ok(h) => test_hands.push((canonicalize(&h), s)),
+            Err(e) => fail(json_output, format!("Could not parse hand: {} ({})", s, e)),
+        }
+    }
+
+    if !json_output {
+        eprintln!("╔══════════════════════════════════════════════╗");
+        eprintln!("║       PLO5 Variance Reduction Report         ║");
+        eprintln!("╚══════════════════════════════════════════════╝");
+        eprintln!("  Precompute: {} boards × {} villains, random vs stratified flops", boards, villain_samples);
+        eprintln!("  Equity:     ~{} trials, plain vs --iso strata", trials);
+        eprintln!("  Replicates: {} per estimator (seed {})", replicates, seed);
+        eprintln!();
+    }
+
+    let t0 = Instant::now();
+    let table = init_eval_table();
+    let evaluator = selected_evaluator(&table);
+    let streams = EngineRng::streams(seed, replicates, |r| mix_seed(seed.wrapping_add(r as u64)));
+
+    if !json_output {
+        eprintln!("  {:<12}  {:>9}  {:>8}  {:>8}  {:>7}  {:>9}  {:>8}  {:>8}  {:>7}",
+            "Hand", "Precomp%", "RandSD", "StratSD", "VarRed", "Equity%", "PlainSD", "IsoSD", "VarRed");
+        eprintln!("  ────────────  ─────────  ────────  ────────  ───────  ─────────  ────────  ────────  ───────");
+    }
+
+    let mut rows: Vec<String> = Vec::new();
+    let mut pooled = [[0.0f64; 2]; 2];
+    for (hand, label) in &test_hands {
+        let precompute: Vec<f64> = parallel_map(2 * replicates, |k| {
+            let mut rng = streams[k / 2].clone();
+            let (sum, count) = sample_hero_equity(hand, boards, villain_samples, 1, k % 2 == 1, &mut rng, evaluator.as_ref());
+            sum / count as f64
+        });
+        let (random_mean, random_sd) = mean_sd(&precompute.iter().copied().step_by(2).collect::<Vec<_>>());
+        let (strat_mean, strat_sd) = mean_sd(&precompute.iter().copied().skip(1).step_by(2).collect::<Vec<_>>());
+
+        let remaining: Vec<u8> = (0..52u8).filter(|c| !hand.contains(c)).collect();
+        let iso = iso_strata(hand, &[], &[], trials, &[]);
+        let planned = iso.last().map_or(0, |st| st.end);
+        let plain = [Stratum { cards: Vec::new(), end: planned, weight: 1.0 }];
+        let equity_runs = |strata: &[Stratum]| -> Vec<f64> {
+            (0..replicates).map(|r| {
+                let tally = equity_trials(&EquitySetup {
+                    hand: *hand,
+                    board_cards: &[],
+                    excluded_bm: card_bitmap(hand),
+                    remaining: &remaining,
+                    strata,
+                    rank_pool: &[],
+                    board_to_fill: 5,
+                    seed: seed.wrapping_add(r as u64),
+                    evaluator: evaluator.as_ref(),
+                    threads: num_threads(),
+                    legacy_rng: LEGACY_RNG.load(Ordering::Relaxed),
+                });
+                let ws = tally.weighted;
+                (ws.0 + 0.5 * ws.1) / ws.2
+            }).collect()
+        };
+        let (plain_mean, plain_sd) = mean_sd(&equity_runs(&plain));
+        let (iso_mean, iso_sd) = mean_sd(&equity_runs(&iso));
+
+        let ratio = |a: f64, b: f64| if b > 0.0 { (a * a) / (b * b) } else { f64::INFINITY };
+        pooled[0][0] += random_sd * random_sd;
+        pooled[0][1] += strat_sd * strat_sd;
+        pooled[1][0] += plain_sd * plain_sd;
+        pooled[1][1] += iso_sd * iso_sd;
+
+        if !json_output {
+            eprintln!("  {:<12}  {:>8.3}%  {:>7.4}%  {:>7.4}%  {:>6.2}x  {:>8.3}%  {:>7.4}%  {:>7.4}%  {:>6.2}x",
+                label, strat_mean * 100.0, random_sd * 100.0, strat_sd * 100.0, ratio(random_sd, strat_sd),
+                iso_mean * 100.0, plain_sd * 100.0, iso_sd * 100.0, ratio(plain_sd, iso_sd));
+        }
+        rows.push(format!(
+            "{{\"hand\":\"{}\",\"precompute\":{{\"randomMean\":{:.6},\"randomSd\":{:.6},\"stratifiedMean\":{:.6},\"stratifiedSd\":{:.6},\"varianceRatio\":{:.4}}},\"equity\":{{\"trials\":{},\"plainMean\":{:.6},\"plainSd\":{:.6},\"isoMean\":{:.6},\"isoSd\":{:.6},\"varianceRatio\":{:.4}}}}}",
+            label, random_mean, random_sd, strat_mean, strat_sd, ratio(random_sd, strat_sd),
+            planned, plain_mean, plain_sd, iso_mean, iso_sd, ratio(plain_sd, iso_sd)));
+    }
+
+    let precompute_ratio = pooled[0][0] / pooled[0][1];
+    let equity_ratio = pooled[1][0] / pooled[1][1];
+    let elapsed_ms = t0.elapsed().as_millis();
+    if json_output {
+        println!("{{\"ok\":true,\"boards\":{},\"villainSamples\":{},\"trials\":{},\"replicates\":{},\"seed\":{},\"hands\":[{}],\"precomputeVarianceRatio\":{:.4},\"equityVarianceRatio\":{:.4},\"elapsedMs\":{}}}",
+            boards, villain_samples, trials, replicates, seed, rows.join(","), precompute_ratio, equity_ratio, elapsed_ms);
+    } else {
+        let showdowns = boards as f64 * villain_samples as f64;
+        eprintln!();
+        eprintln!("  Pooled variance reduction:");
+        eprintln!("    precompute --sampling stratified: {:.2}x (same SE with ~{:.0} instead of {:.0} showdowns/hero)",
+            precompute_ratio, showdowns / precompute_ratio, showdowns);
+        eprintln!("    equity --iso:                     {:.2}x", equity_ratio);
+        eprintln!("  Total time: {:.1}s", elapsed_ms as f64 / 1000.0);
+    }
+}
+
+/// One result of the evaluator self-test.
+struct SelfTestCheck {
+    name: &'static str,
+    pass: bool,
+    detail: String,
+}
+
+/// Combos per category as documented in the README, with the royal flushes
+/// split out of the straight flushes: (name, combos, distinct ranks).
+const DOCUMENTED_CATEGORY_COUNTS: [(&str, u32, u32); 10] = [
+    ("Royal Flush", 4, 1),
+    ("Straight Flush", 36, 9),
+    ("Four of a Kind", 624, 156),
+    ("Full House", 3744, 156),
+    ("Flush", 5108, 1277),
+    ("Straight", 10200, 10),
+    ("Three of a Kind", 54912, 858),
+    ("Two Pair", 123552, 858),
+    ("One Pair", 1098240, 2860),
+    ("High Card", 1302540, 1277),
+];
+
+/// Checks the eval table against the documented totals: 7,462 dense ranks,
+/// the README's combo count per category, and wheel straights.
+fn eval_table_checks(table: &[u16]) -> Vec<SelfTestCheck> {
+    let mut checks = Vec::new();
+    let mut rank_combos = vec![0u32; 7462];
+    let mut out_of_range = 0u32;
+    for &rank in table {
+        match rank_combos.get_mut(rank as usize) {
+            Some(n) => *n += 1,
+            None => out_of_range += 1,
+        }
+    }
+    let distinct = rank_combos.iter().filter(|&&n| n > 0).count();
+    checks.push(SelfTestCheck {
+        name: "distinct ranks",
+        pass: table.len() == 2598960 && distinct == 7462 && out_of_range == 0,
+        detail: format!("{} hands in {} distinct ranks 0..7461, {} out of range", table.len(), distinct, out_of_range),
+    });
+
+    // Categories come from `reference_hand_value`, not the table's own
+    // CATEGORY_RANK_ENDS, so a sort-key bug cannot hide here. Distinct ranks
+    // are the table ranks seen in each category.
+    let mut counted = [(0u32, 0u32); 10];
+    let mut rank_slot = vec![usize::MAX; 7462];
+    for (idx, &rank) in table.iter().enumerate() {
+        let (category, tiebreak) = reference_hand_value(&index_to_hand(idx as u32));
+        let slot = if category == 8 && tiebreak[0] == 12 { 0 } else { 9 - category as usize };
+        counted[slot].0 += 1;
+        if let Some(seen) = rank_slot.get_mut(rank as usize) {
+            if *seen == usize::MAX {
+                *seen = slot;
+                counted[slot].1 += 1;
+            }
+        }
+    }
+    let mismatches: Vec<String> = DOCUMENTED_CATEGORY_COUNTS.iter().zip(&counted)
+        .filter(|((_, combos, ranks), got)| (*combos, *ranks) != **got)
+        .map(|((name, combos, ranks), got)| format!("{}: {} combos / {} ranks, expected {} / {}",
+            name, got.0, got.1, combos, ranks))
+        .collect();
+    checks.push(SelfTestCheck {
+        name: "category counts",
+        pass: mismatches.is_empty(),
+        detail: if mismatches.is_empty() {
+            "royal 4, straight flush 36, quads 624, full house 3,744, flush 5,108, straight 10,200, \
+             trips 54,912, two pair 123,552, pair 1,098,240, high card 1,302,540".into()
+        } else {
+            mismatches.join("; ")
+        },
+    });
+
+    // 5c4d3h2sAc is the lowest straight, below 6-high; the steel wheel is the
+    // lowest straight flush; and straights do not wrap around the Ace.
+    let rank_of = |s: &str| parse_hand(s).map(|h| table[comb_index(&h)]).unwrap_or(u16::MAX);
+    let wheel = rank_of("Ac2s3h4d5c");
+    let six_high = rank_of("2s3h4d5c6c");
+    let steel_wheel = rank_of("Ac2c3c4c5c");
+    let wrap = rank_of("QcKdAh2s3c");
+    let wheel_ok = rank_to_category(wheel) == 4 && wheel == category_rank_ends()[4] - 1 && wheel > six_high
+        && steel_wheel == category_rank_ends()[0] - 1
+        && rank_to_category(wrap) == 8;
+    checks.push(SelfTestCheck {
+        name: "wheel straights",
+        pass: wheel_ok,
+        detail: format!("A-5 rank {} (6-high {}), steel wheel rank {}, Q-K-A-2-3 is {}",
+            wheel, six_high, steel_wheel, CATEGORY_NAMES[rank_to_category(wrap) as usize]),
+    });
+    checks
+}
+
+/// Independent 5-card value for the self-test: (category, tiebreak ranks),
+/// larger is better. Rank groups are ordered by size, then rank, so the
+/// tiebreak reads like the hand ("KKK77", "AAJJ9").
+fn reference_hand_value(cards: &[u8; 5]) -> (u8, [u8; 5]) {
+    let mut counts = [0u8; 13];
+    for &c in cards {
+        counts[(c % 13) as usize] += 1;
+    }
+    let mut groups: Vec<(u8, u8)> = (0..13u8).filter(|&r| counts[r as usize] > 0)
+        .map(|r| (counts[r as usize], r)).collect();
+    groups.sort_by(|a, b| b.cmp(a));
+    let mut tiebreak = [0u8; 5];
+    let mut i = 0;
+    for &(count, rank) in &groups {
+        for _ in 0..count {
+            tiebreak[i] = rank;
+            i += 1;
+        }
+    }
+    let flush = cards.iter().all(|&c| c / 13 == cards[0] / 13);
+    let straight_high = if groups.len() == 5 && tiebreak[0] - tiebreak[4] == 4 {
+        Some(tiebreak[0])
+    } else if groups.len() == 5 && tiebreak == [12, 3, 2, 1, 0] {
+        Some(3)
+    } else {
+        None
+    };
+    let category = match (straight_high, flush, groups[0].0, groups.len()) {
+        (Some(_), true, _, _) => 8,
+        (_, _, 4, _) => 7,
+        (_, _, 3, 2) => 6,
+        (_, true, _, _) => 5,
+        (Some(_), _, _, _) => 4,
+        (_, _, 3, _) => 3,
+        (_, _, 2, 3) => 2,
+        (_, _, 2, _) => 1,
+        _ => 0,
+    };
+    match straight_high {
+        Some(high) if category == 8 || category == 4 => (category, [high, 0, 0, 0, 0]),
+        _ => (category, tiebreak),
+    }
+}
+
+/// Brute-force Omaha value: every 2-of-5 hole and 3-of-5 board choice,
+/// without the engine's subset tables or eval table.
+fn reference_omaha_value(hand: &[u8; 5], board: &[u8; 5]) -> (u8, [u8; 5]) {
+    let mut best = (0u8, [0u8; 5]);
+    for a in 0..5 {
+        for b in (a + 1)..5 {
+            for x in 0..5 {
+                for y in (x + 1)..5 {
+                    for z in (y + 1)..5 {
+                        let value = reference_hand_value(&[hand[a], hand[b], board[x], board[y], board[z]]);
+                        best = best.max(value);
+                    }
+                }
+            }
+        }
+    }
+    best
+}
+
+/// Deals `deals` random hero/villain/board triples and checks that
+/// `eval_best` agrees with the brute-force reference on every showdown
+/// result and on both players' categories.
+fn omaha_reference_check(table: &[u16], deals: u32, seed: u64) -> SelfTestCheck {
+    let mut rng = EngineRng::seeded(mix_seed(seed));
+    let deck: Vec<u8> = (0..52u8).collect();
+    let mut mismatches = 0u32;
+    let mut example = String::new();
+    for _ in 0..deals {
+        let cards = sample_n(&deck, 15, &mut rng);
+        let mut hero: [u8; 5] = cards[0..5].try_into().unwrap();
+        let mut villain: [u8; 5] = cards[5..10].try_into().unwrap();
+        let mut board: [u8; 5] = cards[10..15].try_into().unwrap();
+        hero.sort();
+        villain.sort();
+        board.sort();
+        let board_3s = three_card_subsets(&board);
+        let hero_rank = eval_best(&two_card_subsets(&hero), &board_3s, table);
+        let villain_rank = eval_best(&two_card_subsets(&villain), &board_3s, table);
+        let hero_ref = reference_omaha_value(&hero, &board);
+        let villain_ref = reference_omaha_value(&villain, &board);
+        // Engine ranks are best-first; reference categories count up.
+        let agree = hero_rank.cmp(&villain_rank) == villain_ref.cmp(&hero_ref)
+            && rank_to_category(hero_rank) == 8 - hero_ref.0
+            && rank_to_category(villain_rank) == 8 - villain_ref.0;
+        if !agree {
+            mismatches += 1;
+            if example.is_empty() {
+                let names = |cs: &[u8]| cs.iter().map(|&c| card_name(c)).collect::<String>();
+                example = format!(", first: {} vs {} on {}", names(&hero), names(&villain), names(&board));
+            }
+        }
+    }
+    SelfTestCheck {
+        name: "eval_best vs brute force",
+        pass: mismatches == 0,
+        detail: format!("{} random showdowns, {} mismatches{}", deals, mismatches, example),
+    }
+}
+
+fn run_selftest(args: &[String]) {
+    let deals: u32 = parse_flag(args, "--deals")
+        .and_then(|s| s.parse().ok()).unwrap_or(200_000);
+    let seed: u64 = parse_flag(args, "--seed")
+        .and_then(|s| s.parse().ok()).unwrap_or(12345);
+    let json_output = args.iter().any(|a| a == "--json");
+
+    let t0 = Instant::now();
+    let table = init_eval_table();
+    let mut checks = eval_table_checks(&table);
+    checks.push(omaha_reference_check(&table, deals, seed));
+    let pass = checks.iter().all(|c| c.pass);
+
+    if json_output {
+        let items: Vec<String> = checks.iter().map(|c| format!("{{\"name\":\"{}\",\"pass\":{},\"detail\":\"{}\"}}",
+            c.name, c.pass, c.detail)).collect();
+        println!("{{\"ok\":true,\"pass\":{},\"checks\":[{}],\"elapsedMs\":{}}}",
+            pass, items.join(","), t0.elapsed().as_millis());
+        return;
+    }
+
+    eprintln!("╔══════════════════════════════════════════════╗");
+    eprintln!("║            Evaluator Self-Test                ║");
+    eprintln!("╚══════════════════════════════════════════════╝");
+    for c in &checks {
+        eprintln!("  {} {:<26} {}", if c.pass { "✓" } else { "✗" }, c.name, c.detail);
+    }
+    eprintln!();
+    eprintln!("  {} in {:.1}s", if pass { "✓ ALL PASSED" } else { "✗ FAILED" }, t0.elapsed().as_secs_f64());
+    if !pass {
+        std::process::exit(1);
+    }
+}
+
 fn run_info() {
     let t0 = Instant::now();
     eprintln!("PLO5 Ranker Engine v2.0");
@@ -1090,6 +3130,9 @@ fn run_info() {
     eprintln!("  Total combos: {} (expected 2598960)", total_combos);
     eprintln!("  Boards per hero: C(47,5) = {}", BINOM[47][5]);
     eprintln!("  CPUs detected: {}", num_cpus());
+    if let Some(quota) = cgroup_cpu_quota() {
+        eprintln!("  cgroup quota:  {} CPUs", quota);
+    }
 
     let t1 = Instant::now();
     eprintln!();
@@ -1116,6 +3159,31 @@ fn run_info() {
     }
 }
 
+/// Opponent count a rankings file was computed against. Files written before
+/// multiway support leave the field zeroed and are heads-up.
+fn bin_opponents(data: &[u8]) -> u32 {
+    data.get(40..44).map_or(1, |b| u32::from_le_bytes(b.try_into().unwrap()).max(1))
+}
+
+/// How a rankings file dealt its boards, stored at header offset 44.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum BoardSharing {
+    /// Written before the field existed, when CRN was already the default.
+    Unknown = 0,
+    /// Every hand drew boards of its own (`--crn off`, or `--boards full`).
+    Independent = 1,
+    /// Every hand played the same CRN scenario boards (also exact villains).
+    Shared = 2,
+}
+
+fn bin_board_sharing(data: &[u8]) -> BoardSharing {
+    match data.get(44..48).map(|b| u32::from_le_bytes(b.try_into().unwrap())) {
+        Some(1) => BoardSharing::Independent,
+        Some(2) => BoardSharing::Shared,
+        _ => BoardSharing::Unknown,
+    }
+}
+
 fn print_bin_info(path: &str) {
     let data = fs::read(path).unwrap();
     if data.len() < 64 || &data[0..4] != b"PLO5" {
@@ -1130,15 +3198,26 @@ fn print_bin_info(path: &str) {
     let min = u32::from_le_bytes(data[24..28].try_into().unwrap());
     let max = u32::from_le_bytes(data[28..32].try_into().unwrap());
     let ts = i64::from_le_bytes(data[32..40].try_into().unwrap());
+    let opponents = bin_opponents(&data);
 
     eprintln!("Production binary: {}", path);
     eprintln!("  Version:         {}", version);
     eprintln!("  Hands:           {}", num_hands);
     eprintln!("  Boards/hero:     {}", boards);
-    eprintln!("  Villain samples: {}", v_samples);
+    if v_samples == 0 {
+        eprintln!("  Villain samples: exact (samples below count boards)");
+    } else {
+        eprintln!("  Villain samples: {}", v_samples);
+    }
     eprintln!("  Avg samples:     {}", avg);
     eprintln!("  Min samples:     {}", min);
     eprintln!("  Max samples:     {}", max);
+    eprintln!("  Opponents:       {}", opponents);
+    eprintln!("  Boards:          {}", match bin_board_sharing(&data) {
+        BoardSharing::Independent => "independent per hand",
+        BoardSharing::Shared => "shared CRN scenarios",
+        BoardSharing::Unknown => "not recorded",
+    });
     eprintln!("  Timestamp:       {}", ts);
     eprintln!("  File size:       {} bytes ({:.2} MB)", data.len(), data.len() as f64 / 1e6);
 }
@@ -1173,6 +3252,10 @@ fn validate_rank_index(rank_index: &[u32], bin_path: &str) -> bool {
         return true;
     }
     let num_hands = u32::from_le_bytes(bin_data[8..12].try_into().unwrap()) as usize;
+    // Equities shrink with more opponents, so the bounds scale with the fair
+    // share: >60% and <20% heads-up.
+    let fair_share = 1.0 / (bin_opponents(&bin_data) + 1) as f32;
+    let (top_min, bot_max) = (1.2 * fair_share, 0.4 * fair_share);
     let mut canonical_equity: HashMap<[u8; 5], f32> = HashMap::with_capacity(num_hands);
     for i in 0..num_hands {
         let off = 64 + i * 20;
@@ -1191,13 +3274,13 @@ fn validate_rank_index(rank_index: &[u32], bin_path: &str) -> bool {
     let bot_can = canonicalize(&bot_hand);
     let bot_eq = canonical_equity.get(&bot_can).copied().unwrap_or(1.0);
 
-    let valid = top_eq > 0.60 && bot_eq < 0.20;
+    let valid = top_eq > top_min && bot_eq < bot_max;
     if !valid {
         eprintln!("  FATAL: Rank index sanity check FAILED!");
-        eprintln!("    Top hand: {} eq={:.3}% (expected >60%)",
-            top_hand.iter().map(|&c| card_name(c)).collect::<Vec<_>>().join(""), top_eq * 100.0);
-        eprintln!("    Bottom hand: {} eq={:.3}% (expected <20%)",
-            bot_hand.iter().map(|&c| card_name(c)).collect::<Vec<_>>().join(""), bot_eq * 100.0);
+        eprintln!("    Top hand: {} eq={:.3}% (expected >{:.0}%)",
+            top_hand.iter().map(|&c| card_name(c)).collect::<Vec<_>>().join(""), top_eq * 100.0, top_min * 100.0);
+        eprintln!("    Bottom hand: {} eq={:.3}% (expected <{:.0}%)",
+            bot_hand.iter().map(|&c| card_name(c)).collect::<Vec<_>>().join(""), bot_eq * 100.0, bot_max * 100.0);
         eprintln!("  Rank index file appears to have garbage rankings. Regenerate with:");
         eprintln!("    plo5_ranker build_rank_index --bin <prod.bin> --out <rank_index.u32>");
     }
@@ -1212,9 +3295,313 @@ fn parse_villain_range(s: &str) -> Option<f64> {
     s.parse::<f64>().ok().filter(|&v| v > 0.0 && v <= 100.0)
 }
 
+/// Trials per random stream in `run_equity`. Streams are seeded by block
+/// index rather than thread id, so results do not depend on how many threads
+/// share the blocks.
+const TRIAL_BLOCK: usize = 4096;
+
+/// A run of consecutive `run_equity` trials that start from the same extra
+/// board cards. Without --iso there is one stratum covering everything; with
+/// it, each isomorphism class of the next street's cards gets trials in
+/// proportion to its weight, and each trial is reweighted so the estimate
+/// stays unbiased.
+struct Stratum {
+    cards: Vec<u8>,
+    end: usize,
+    weight: f64,
+}
+
+/// The --iso strata: one per isomorphism class of the flop (preflop) or of
+/// the next card, with exactly `trials` trials between them (at least one
+/// each). A class is weighted by its share of the deals: its card count for a
+/// random villain, or the range hands each of its boards leaves live when
+/// `rank_pool` is a restricted range.
+fn iso_strata(hand: &[u8; 5], board_cards: &[u8], dead_cards: &[u8], trials: usize,
+              rank_pool: &[[u8; 5]]) -> Vec<Stratum> {
+    let fixed: [&[u8]; 3] = [hand, board_cards, dead_cards];
+    let class_key = |cards: &[u8]| match *cards {
+        [c] => board_class_key(&fixed, &[c]),
+        [a, b, c] => board_class_key(&fixed, &[a, b, c]),
+        _ => unreachable!("iso strata deal one or three cards"),
+    };
+    let mut classes: Vec<(Vec<u8>, u64)> = if board_cards.is_empty() {
+        board_classes::<3>(&fixed).into_iter().map(|(b, w)| (b.to_vec(), w as u64)).collect()
+    } else {
+        board_classes::<1>(&fixed).into_iter().map(|(b, w)| (b.to_vec(), w as u64)).collect()
+    };
+    if !rank_pool.is_empty() {
+        let depth = classes[0].0.len();
+        let excluded_bm = fixed.iter().fold(0u64, |bm, g| bm | card_bitmap(g));
+        let blockers = RangeBlockers::new(rank_pool, excluded_bm, depth);
+        let slot: HashMap<usize, usize> = classes.iter().enumerate()
+            .map(|(i, (cards, _))| (class_key(cards), i))
+            .collect();
+        for class in classes.iter_mut() { class.1 = 0; }
+        let unseen: Vec<u8> = (0..52u8).filter(|&c| excluded_bm & (1u64 << c) == 0).collect();
+        for_each_subset(&unseen, depth, |cards| {
+            classes[slot[&class_key(cards)]].1 += blockers.live(cards);
+        });
+    }
+    let total_weight = classes.iter().map(|&(_, w)| w).sum::<u64>().max(1);
+    // One trial per class, then the rest by largest remainder.
+    let spare = trials.saturating_sub(classes.len()) as f64;
+    let shares: Vec<f64> = classes.iter().map(|&(_, w)| spare * w as f64 / total_weight as f64).collect();
+    let mut class_trials: Vec<usize> = shares.iter().map(|s| 1 + s.floor() as usize).collect();
+    let short = trials.saturating_sub(class_trials.iter().sum());
+    let mut by_remainder: Vec<usize> = (0..classes.len()).collect();
+    by_remainder.sort_by(|&a, &b| shares[b].fract().total_cmp(&shares[a].fract()));
+    for &i in by_remainder.iter().take(short) { class_trials[i] += 1; }
+    let planned: usize = class_trials.iter().sum();
+    let mut end = 0;
+    classes.into_iter().zip(class_trials).map(|((cards, w), n)| {
+        end += n;
+        let weight = w as f64 / total_weight as f64 * planned as f64 / n as f64;
+        Stratum { cards, end, weight }
+    }).collect()
+}
+
+/// Calls `f` on every `k`-card subset of `pool`, in order.
+fn for_each_subset(pool: &[u8], k: usize, mut f: impl FnMut(&[u8])) {
+    if pool.len() < k { return; }
+    let mut idx: Vec<usize> = (0..k).collect();
+    let mut cards: Vec<u8> = idx.iter().map(|&i| pool[i]).collect();
+    loop {
+        f(&cards);
+        let Some(i) = (0..k).rev().find(|&i| idx[i] < pool.len() - k + i) else { return };
+        idx[i] += 1;
+        for j in (i + 1)..k { idx[j] = idx[j - 1] + 1; }
+        for j in i..k { cards[j] = pool[idx[j]]; }
+    }
+}
+
+/// Range hands that avoid the known cards, and how many of them hold each
+/// card, pair and triple, so the hands still live after up to `depth` more
+/// cards are dealt follow by inclusion-exclusion.
+struct RangeBlockers {
+    total: u64,
+    one: Vec<u64>,
+    two: Vec<u64>,
+    three: Vec<u64>,
+}
+
+impl RangeBlockers {
+    fn new(rank_pool: &[[u8; 5]], excluded_bm: u64, depth: usize) -> Self {
+        let mut blockers = RangeBlockers {
+            total: 0,
+            one: vec![0; 52],
+            two: vec![0; if depth >= 2 { 52 * 52 } else { 0 }],
+            three: vec![0; if depth >= 3 { 52 * 52 * 52 } else { 0 }],
+        };
+        for hand in rank_pool.iter().filter(|h| card_bitmap(&h[..]) & excluded_bm == 0) {
+            let mut hand = *hand;
+            hand.sort_unstable();
+            blockers.total += 1;
+            for (i, &a) in hand.iter().enumerate() {
+                blockers.one[a as usize] += 1;
+                if depth < 2 { continue; }
+                for (j, &b) in hand.iter().enumerate().skip(i + 1) {
+                    blockers.two[a as usize * 52 + b as usize] += 1;
+                    if depth < 3 { continue; }
+                    for &c in &hand[j + 1..] {
+                        blockers.three[(a as usize * 52 + b as usize) * 52 + c as usize] += 1;
+                    }
+                }
+            }
+        }
+        blockers
+    }
+
+    /// Hands that avoid every card of `cards` (sorted, at most `depth`).
+    fn live(&self, cards: &[u8]) -> u64 {
+        let mut live = self.total as i64;
+        for mask in 1u32..(1 << cards.len()) {
+            let picked: Vec<usize> = (0..cards.len()).filter(|&i| mask & (1 << i) != 0)
+                .map(|i| cards[i] as usize).collect();
+            let held = match picked[..] {
+                [a] => self.one[a],
+                [a, b] => self.two[a * 52 + b],
+                [a, b, c] => self.three[(a * 52 + b) * 52 + c],
+                _ => unreachable!("at most three dealt cards"),
+            } as i64;
+            live += if picked.len() % 2 == 1 { -held } else { held };
+        }
+        live as u64
+    }
+}
+
+/// Weighted (showdowns, wins, ties) by each player's final hand category.
+#[derive(Clone, Copy)]
+struct CategoryTally {
+    hero: [[f64; 3]; 9],
+    villain: [[f64; 3]; 9],
+}
+
+impl CategoryTally {
+    fn record(&mut self, hero_rank: u16, villain_rank: u16, weight: f64) {
+        let hero = &mut self.hero[rank_to_category(hero_rank) as usize];
+        let villain = &mut self.villain[rank_to_category(villain_rank) as usize];
+        hero[0] += weight;
+        villain[0] += weight;
+        if hero_rank < villain_rank { hero[1] += weight; }
+        else if hero_rank > villain_rank { villain[1] += weight; }
+        else { hero[2] += weight; villain[2] += weight; }
+    }
+    fn merge(mut self, other: &CategoryTally) -> CategoryTally {
+        for (a, b) in self.hero.iter_mut().chain(self.villain.iter_mut())
+            .zip(other.hero.iter().chain(other.villain.iter())) {
+            for k in 0..3 { a[k] += b[k]; }
+        }
+        self
+    }
+}
+
+/// Counts from a run of equity trials; `weighted` holds (wins, ties, total)
+/// after stratum weights.
+#[derive(Clone, Copy)]
+struct EquityTally {
+    wins: u64,
+    ties: u64,
+    total: u64,
+    weighted: (f64, f64, f64),
+    categories: CategoryTally,
+}
+
+impl EquityTally {
+    fn new() -> Self {
+        EquityTally {
+            wins: 0,
+            ties: 0,
+            total: 0,
+            weighted: (0.0, 0.0, 0.0),
+            categories: CategoryTally { hero: [[0.0; 3]; 9], villain: [[0.0; 3]; 9] },
+        }
+    }
+    fn record(&mut self, hero_rank: u16, villain_rank: u16, weight: f64) {
+        if hero_rank < villain_rank { self.wins += 1; self.weighted.0 += weight; }
+        else if hero_rank == villain_rank { self.ties += 1; self.weighted.1 += weight; }
+        self.total += 1;
+        self.weighted.2 += weight;
+        self.categories.record(hero_rank, villain_rank, weight);
+    }
+    fn merge(self, other: &EquityTally) -> EquityTally {
+        EquityTally {
+            wins: self.wins + other.wins,
+            ties: self.ties + other.ties,
+            total: self.total + other.total,
+            weighted: (self.weighted.0 + other.weighted.0, self.weighted.1 + other.weighted.1,
+                self.weighted.2 + other.weighted.2),
+            categories: self.categories.merge(&other.categories),
+        }
+    }
+}
+
+/// Everything the trial blocks of one `run_equity` call share. An empty
+/// `rank_pool` means villain is uniform over the remaining cards.
+struct EquitySetup<'a> {
+    hand: [u8; 5],
+    board_cards: &'a [u8],
+    excluded_bm: u64,
+    remaining: &'a [u8],
+    strata: &'a [Stratum],
+    rank_pool: &'a [[u8; 5]],
+    board_to_fill: usize,
+    seed: u64,
+    evaluator: &'a dyn OmahaEvaluator,
+    /// Worker threads for the trial blocks, normally `num_threads()`.
+    threads: usize,
+    /// `--rng legacy`: split the trials as the old engine did, see
+    /// `equity_trials`.
+    legacy_rng: bool,
+}
+
+/// Plays every planned trial in blocks of `TRIAL_BLOCK` on `setup.threads`.
+/// Block `b` draws from its own stream (the `b`-th jump of the seed), and
+/// blocks are summed in order, so a seed gives bit-identical results on any
+/// thread count. With `legacy_rng` there is one block per thread instead, on
+/// the Xorshift64 stream `mix_seed(seed + t)`, which reproduces older engines
+/// run with the same number of threads.
+fn equity_trials(setup: &EquitySetup) -> EquityTally {
+    let planned = setup.strata.last().map_or(0, |st| st.end);
+    let block = if setup.legacy_rng { planned.div_ceil(setup.threads).max(1) } else { TRIAL_BLOCK };
+    let num_blocks = planned.div_ceil(block);
+    let block_rngs = EngineRng::streams_for(setup.legacy_rng, setup.seed, num_blocks,
+        |b| mix_seed(setup.seed.wrapping_add(b as u64)));
+    parallel_map_with(setup.threads, num_blocks, |b| {
+        equity_block(setup, b * block, ((b + 1) * block).min(planned), block_rngs[b].clone())
+    }).iter().fold(EquityTally::new(), |acc, block| acc.merge(block))
+}
+
+fn equity_block(setup: &EquitySetup, start: usize, end: usize, mut rng: EngineRng) -> EquityTally {
+    let mut tally = EquityTally::new();
+    let mut si = usize::MAX;
+    let mut stratum_board: Vec<u8> = Vec::new();
+    let mut stratum_remaining: Vec<u8> = Vec::new();
+    let mut excl_bm = 0u64;
+    let mut board_fill_n = 0;
+    let mut weight = 1.0;
+    for trial in start..end {
+        if si == usize::MAX || trial >= setup.strata[si].end {
+            si = setup.strata.iter().position(|st| trial < st.end).unwrap();
+            let stratum = &setup.strata[si];
+            stratum_board = setup.board_cards.to_vec();
+            stratum_board.extend_from_slice(&stratum.cards);
+            excl_bm = setup.excluded_bm | card_bitmap(&stratum.cards);
+            stratum_remaining = setup.remaining.iter().copied()
+                .filter(|&c| excl_bm & (1u64 << c) == 0)
+                .collect();
+            board_fill_n = setup.board_to_fill - stratum.cards.len();
+            weight = stratum.weight;
+        }
+        if !setup.rank_pool.is_empty() {
+            let pool_len = setup.rank_pool.len();
+            let mut found = false;
+            let mut villain_arr = [0u8; 5];
+            for _ in 0..200 {
+                let vi = rng.gen_range(pool_len);
+                let cand = setup.rank_pool[vi];
+                let cand_bm = card_bitmap(&cand);
+                if cand_bm & excl_bm != 0 { continue; }
+                villain_arr = cand;
+                found = true;
+                break;
+            }
+            if !found { continue; }
+            let villain_bm = card_bitmap(&villain_arr);
+            let all_used_bm = excl_bm | villain_bm;
+            let fill_pool: Vec<u8> = (0..52u8)
+                .filter(|&c| all_used_bm & (1u64 << (c as u64)) == 0)
+                .collect();
+            if fill_pool.len() < board_fill_n { continue; }
+            let board_sample = sample_n(&fill_pool, board_fill_n, &mut rng);
+            let mut full_board = stratum_board.clone();
+            full_board.extend_from_slice(&board_sample);
+            full_board.sort();
+            let board: [u8; 5] = full_board.try_into().unwrap();
+            let hero_rank = setup.evaluator.best_rank(&setup.hand, &board);
+            let villain_rank = setup.evaluator.best_rank(&villain_arr, &board);
+            tally.record(hero_rank, villain_rank, weight);
+        } else {
+            let board_sample = sample_n(&stratum_remaining, board_fill_n, &mut rng);
+            let mut full_board = stratum_board.clone();
+            full_board.extend_from_slice(&board_sample);
+            full_board.sort();
+            let board: [u8; 5] = full_board.try_into().unwrap();
+            let board_bm = card_bitmap(&board);
+            let hero_rank = setup.evaluator.best_rank(&setup.hand, &board);
+            let pool: Vec<u8> = stratum_remaining.iter()
+                .filter(|&&c| board_bm & (1u64 << (c as u64)) == 0)
+                .copied().collect();
+            let villain_arr = sample_villain(&pool, &mut rng);
+            let villain_rank = setup.evaluator.best_rank(&villain_arr, &board);
+            tally.record(hero_rank, villain_rank, weight);
+        }
+    }
+    tally
+}
+
 fn run_equity(args: &[String]) {
     let hand_str = parse_flag(args, "--hand").unwrap_or_else(|| {
-        eprintln!("Usage: plo5_ranker equity --hand <hand> [--board <cards>] [--dead <cards>] [--trials N] [--seed S] [--json] [--villain-range 100%|N%] [--rank-file path]");
+        eprintln!("Usage: plo5_ranker equity --hand <hand> [--board <cards>] [--dead <cards>] [--trials N] [--seed S] [--iso] [--categories] [--threads N] [--json] [--villain-range 100%|N%] [--rank-file path]");
         eprintln!("Example: plo5_ranker equity --hand AcAdKhQh5s --trials 600000 --seed 12345 --json");
         eprintln!("Example: plo5_ranker equity --hand AcAdKhQh5s --villain-range 10% --rank-file rank_index_all_2598960.u32 --json");
         std::process::exit(1);
@@ -1228,37 +3615,22 @@ fn run_equity(args: &[String]) {
     let board_str = parse_flag(args, "--board").unwrap_or_default();
     let dead_str = parse_flag(args, "--dead").unwrap_or_default();
     let villain_range_str = parse_flag(args, "--villain-range").unwrap_or_else(|| "100%".into());
-    let rank_file = parse_flag(args, "--rank-file").unwrap_or_else(|| "public/rank_index_all_2598960.u32".into());
+    let (rank_file, bin_path_for_validation) = range_files(args).unwrap_or_else(|msg| fail(json_output, msg));
 
     let villain_pct = parse_villain_range(&villain_range_str).unwrap_or_else(|| {
-        if json_output {
-            println!("{{\"ok\":false,\"error\":\"Invalid villain range: '{}'. Use N% (e.g. 10%, 20%, 100%)\"}}", villain_range_str);
-            std::process::exit(0);
-        }
-        eprintln!("Invalid villain range: '{}'. Use N% (e.g. 10%, 20%, 100%)", villain_range_str);
-        std::process::exit(1);
+        fail(json_output, format!("Invalid villain range: '{}'. Use N% (e.g. 10%, 20%, 100%)", villain_range_str))
     });
     let is_range_restricted = villain_pct < 100.0;
 
-    let hand = parse_hand(&hand_str).unwrap_or_else(|| {
-        if json_output {
-            println!("{{\"ok\":false,\"error\":\"Could not parse hand: {} (need exactly 5 cards)\"}}", hand_str);
-            std::process::exit(0);
-        }
-        eprintln!("Could not parse hand: {}", hand_str);
-        std::process::exit(1);
-    });
-
-    let board_cards = parse_cards_vec(&board_str);
-    let dead_cards = parse_cards_vec(&dead_str);
+    let hand = parse_hand(&hand_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse hand: {} ({})", hand_str, e)));
+    let board_cards = parse_cards(&board_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse board: {} ({})", board_str, e)));
+    let dead_cards = parse_cards(&dead_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse dead cards: {} ({})", dead_str, e)));
 
     if !board_cards.is_empty() && board_cards.len() != 3 && board_cards.len() != 4 && board_cards.len() != 5 {
-        if json_output {
-            println!("{{\"ok\":false,\"error\":\"Board must have 0, 3, 4, or 5 cards (got {})\"}}", board_cards.len());
-            std::process::exit(0);
-        }
-        eprintln!("Board must have 0, 3, 4, or 5 cards (got {})", board_cards.len());
-        std::process::exit(1);
+        fail(json_output, format!("Board must have 0, 3, 4, or 5 cards (got {})", board_cards.len()));
     }
 
     let mut excluded: Vec<u8> = Vec::new();
@@ -1268,31 +3640,22 @@ fn run_equity(args: &[String]) {
     excluded.sort();
     excluded.dedup();
     if excluded.len() != hand.len() + board_cards.len() + dead_cards.len() {
-        if json_output {
-            println!("{{\"ok\":false,\"error\":\"Duplicate cards found among hand, board, and dead cards\"}}");
-            std::process::exit(0);
-        }
-        eprintln!("Duplicate cards found among hand, board, and dead cards");
-        std::process::exit(1);
+        fail(json_output, "Duplicate cards found among hand, board, and dead cards".into());
     }
 
     let canonical = canonicalize(&hand);
     let card_names: Vec<String> = canonical.iter().map(|&c| card_name(c)).collect();
     let board_to_fill = 5 - board_cards.len();
+    let iso_mode = args.iter().any(|a| a == "--iso") && board_to_fill > 0;
+    let show_categories = args.iter().any(|a| a == "--categories");
 
-    let bin_path_for_validation = parse_flag(&args, "--bin")
-        .unwrap_or_else(|| "public/plo5_rankings_prod.bin".into());
     let rank_pool: Vec<[u8; 5]> = if is_range_restricted {
         let rank_index = load_rank_index(&rank_file);
         if !validate_rank_index(&rank_index, &bin_path_for_validation) {
-            if json_output {
-                println!("{{\"ok\":false,\"error\":\"Rank index sanity check failed. Top equity must be >60%, bottom <20%. Regenerate with build_rank_index.\"}}");
-                std::process::exit(0);
-            }
-            std::process::exit(1);
+            fail(json_output, "Rank index sanity check failed. Top equity must be >1.2x the fair share (60% heads-up), bottom <0.4x (20%). Regenerate with build_rank_index.".into());
         }
         let top_count = ((villain_pct / 100.0) * 2598960.0).floor() as usize;
-        let top_count = top_count.max(1).min(2598960);
+        let top_count = top_count.clamp(1, 2598960);
         if !json_output {
             eprintln!("  Villain range: top {:.1}% ({} of 2,598,960 hands)", villain_pct, top_count);
         }
@@ -1317,129 +3680,93 @@ fn run_equity(args: &[String]) {
         eprintln!("  Villain: {}", villain_range_str);
         eprintln!("  Trials:  {}", trials);
         eprintln!("  Seed:    {}", seed);
+        if iso_mode {
+            eprintln!("  Iso:     stratified over isomorphic {} cards",
+                if board_cards.is_empty() { "flop" } else { "next" });
+        }
         eprintln!();
     }
 
     let t0 = Instant::now();
     if !json_output { eprintln!("[1/2] Initializing eval table..."); }
     let table = init_eval_table();
+    let evaluator = selected_evaluator(&table);
     if !json_output { eprintln!("       Done in {:.2}s", t0.elapsed().as_secs_f64()); }
 
     if !json_output { eprintln!("[2/2] Running deterministic MC..."); }
-    let hero_2s = two_card_subsets(&hand);
     let hero_bm = card_bitmap(&hand);
     let remaining: Vec<u8> = (0..52u8).filter(|c| !excluded.contains(c)).collect();
     let mut excluded_bm: u64 = hero_bm;
     for &c in &board_cards { excluded_bm |= 1u64 << (c as u64); }
     for &c in &dead_cards { excluded_bm |= 1u64 << (c as u64); }
 
-    let num_threads = num_cpus();
-    let chunk = (trials as usize + num_threads - 1) / num_threads;
-
-    let (mc_equity, total_wins, total_ties, total_count): (f64, u64, u64, u64) = thread::scope(|s| {
-        let handles: Vec<_> = (0..num_threads).map(|t| {
-            let table_ref = &table;
-            let hero_2s_ref = &hero_2s;
-            let remaining_ref = &remaining;
-            let board_cards_ref = &board_cards;
-            let board_fill_n = board_to_fill;
-            let rank_pool_ref = &rank_pool;
-            let use_range = is_range_restricted;
-            let excl_bm = excluded_bm;
-            s.spawn(move || {
-                let start = t * chunk;
-                let end = ((t + 1) * chunk).min(trials as usize);
-                let mut rng = Xorshift64::new(
-                    mix_seed(seed.wrapping_add(t as u64))
-                );
-                let mut win_count = 0u64;
-                let mut tie_count = 0u64;
-                let mut total = 0u64;
-                for _ in start..end {
-                    if use_range {
-                        let pool_len = rank_pool_ref.len();
-                        let mut found = false;
-                        let mut villain_arr = [0u8; 5];
-                        for _ in 0..200 {
-                            let vi = rng.gen_range(pool_len);
-                            let cand = rank_pool_ref[vi];
-                            let cand_bm = card_bitmap(&cand);
-                            if cand_bm & excl_bm != 0 { continue; }
-                            villain_arr = cand;
-                            found = true;
-                            break;
-                        }
-                        if !found { continue; }
-                        let villain_bm = card_bitmap(&villain_arr);
-                        let all_used_bm = excl_bm | villain_bm;
-                        let fill_pool: Vec<u8> = (0..52u8)
-                            .filter(|&c| all_used_bm & (1u64 << (c as u64)) == 0)
-                            .collect();
-                        if fill_pool.len() < board_fill_n { continue; }
-                        let board_sample = sample_n(&fill_pool, board_fill_n, &mut rng);
-                        let mut full_board = Vec::with_capacity(5);
-                        for &c in board_cards_ref { full_board.push(c); }
-                        for i in 0..board_fill_n { full_board.push(board_sample[i]); }
-                        full_board.sort();
-                        let board_3s = three_card_subsets_from_slice(&full_board);
-                        let hero_rank = eval_best(hero_2s_ref, &board_3s, table_ref);
-                        let villain_2s = two_card_subsets(&villain_arr);
-                        let villain_rank = eval_best(&villain_2s, &board_3s, table_ref);
-                        if hero_rank < villain_rank { win_count += 1; }
-                        else if hero_rank == villain_rank { tie_count += 1; }
-                        total += 1;
-                    } else {
-                        let board_sample = sample_n(remaining_ref, board_fill_n, &mut rng);
-                        let mut full_board = Vec::with_capacity(5);
-                        for &c in board_cards_ref { full_board.push(c); }
-                        for i in 0..board_fill_n { full_board.push(board_sample[i]); }
-                        full_board.sort();
-                        let board_bm = card_bitmap(&full_board);
-                        let board_3s = three_card_subsets_from_slice(&full_board);
-                        let hero_rank = eval_best(hero_2s_ref, &board_3s, table_ref);
-                        let pool: Vec<u8> = remaining_ref.iter()
-                            .filter(|&&c| board_bm & (1u64 << (c as u64)) == 0)
-                            .copied().collect();
-                        let villain_arr = sample_villain(&pool, &mut rng);
-                        let villain_2s = two_card_subsets(&villain_arr);
-                        let villain_rank = eval_best(&villain_2s, &board_3s, table_ref);
-                        if hero_rank < villain_rank { win_count += 1; }
-                        else if hero_rank == villain_rank { tie_count += 1; }
-                        total += 1;
-                    }
-                }
-                (win_count, tie_count, total)
-            })
-        }).collect();
-        let (tw, tt, tc): (u64, u64, u64) = handles.into_iter()
-            .map(|h| h.join().unwrap())
-            .fold((0, 0, 0), |(w, t, c), (w2, t2, c2)| (w + w2, t + t2, c + c2));
-        let eq = (tw as f64 + 0.5 * tt as f64) / tc as f64;
-        (eq, tw, tt, tc)
+    let strata: Vec<Stratum> = if iso_mode {
+        iso_strata(&hand, &board_cards, &dead_cards, trials as usize, &rank_pool)
+    } else {
+        vec![Stratum { cards: Vec::new(), end: trials as usize, weight: 1.0 }]
+    };
+    let planned_trials = strata.last().map_or(0, |st| st.end);
+    if iso_mode && !json_output {
+        eprintln!("       {} isomorphism classes for the next street, {} stratified trials",
+            strata.len(), planned_trials);
+    }
+
+    let num_threads = num_threads();
+    let equity_tally = equity_trials(&EquitySetup {
+        hand,
+        board_cards: &board_cards,
+        excluded_bm,
+        remaining: &remaining,
+        strata: &strata,
+        rank_pool: &rank_pool,
+        board_to_fill,
+        seed,
+        evaluator: evaluator.as_ref(),
+        threads: num_threads,
+        legacy_rng: LEGACY_RNG.load(Ordering::Relaxed),
     });
+    let ws = equity_tally.weighted;
+    let win_pct = ws.0 / ws.2 * 100.0;
+    let tie_pct = ws.1 / ws.2 * 100.0;
+    let mc_equity = (ws.0 + 0.5 * ws.1) / ws.2;
+    let (total_wins, total_ties, total_count) = (equity_tally.wins, equity_tally.ties, equity_tally.total);
+    let tally = equity_tally.categories;
+    let weighted_total: f64 = tally.hero.iter().map(|c| c[0]).sum();
 
     let elapsed = t0.elapsed().as_secs_f64();
     let elapsed_ms = (elapsed * 1000.0) as u64;
     let losses = total_count - total_wins - total_ties;
-    let win_pct = total_wins as f64 / total_count as f64 * 100.0;
-    let tie_pct = total_ties as f64 / total_count as f64 * 100.0;
-    let expected_equity = (total_wins as f64 + 0.5 * total_ties as f64) / total_count as f64;
+    let expected_equity = (win_pct + 0.5 * tie_pct) / 100.0;
     let equity_check_delta = (mc_equity - expected_equity).abs();
     assert!(equity_check_delta < 1e-12,
         "ASSERTION FAILED: equity({}) != (wins + 0.5*ties)/total({}), delta={}",
         mc_equity, expected_equity, equity_check_delta);
 
     if json_output {
-        let rank_info = if is_range_restricted {
+        let mut rank_info = if is_range_restricted {
             let top_count = ((villain_pct / 100.0) * 2598960.0).floor() as usize;
-            let top_count = top_count.max(1).min(2598960);
+            let top_count = top_count.clamp(1, 2598960);
             format!(",\"rankIndexMode\":\"concrete_combo_uniform\",\"totalConcrete\":2598960,\"topK\":{}", top_count)
         } else {
             String::new()
         };
+        if iso_mode {
+            rank_info.push_str(&format!(",\"isoClasses\":{}", strata.len()));
+        }
+        if show_categories {
+            let side = |rows: &[[f64; 3]; 9]| -> String {
+                rows.iter().enumerate().map(|(cat, c)| {
+                    let (win, tie) = if c[0] > 0.0 { (c[1] / c[0], c[2] / c[0]) } else { (0.0, 0.0) };
+                    format!("{{\"name\":\"{}\",\"pct\":{:.4},\"winPct\":{:.4},\"tiePct\":{:.4},\"equity\":{:.6}}}",
+                        CATEGORY_NAMES[cat], c[0] / weighted_total * 100.0, win * 100.0, tie * 100.0, win + 0.5 * tie)
+                }).collect::<Vec<_>>().join(",")
+            };
+            rank_info.push_str(&format!(",\"categories\":{{\"hero\":[{}],\"villain\":[{}]}}",
+                side(&tally.hero), side(&tally.villain)));
+        }
         println!(
-            "{{\"ok\":true,\"equity\":{:.6},\"equityPct\":{:.4},\"winPct\":{:.4},\"tiePct\":{:.4},\"wins\":{},\"ties\":{},\"losses\":{},\"trials\":{},\"seed\":{},\"elapsedMs\":{},\"villainRange\":\"{}\"{}}}",
-            mc_equity, mc_equity * 100.0, win_pct, tie_pct, total_wins, total_ties, losses, total_count, seed, elapsed_ms, villain_range_str, rank_info
+            "{{\"ok\":true,\"equity\":{:.6},\"equityPct\":{:.4},\"winPct\":{:.4},\"tiePct\":{:.4},\"wins\":{},\"ties\":{},\"losses\":{},\"trials\":{},\"seed\":{},\"elapsedMs\":{},\"threadsUsed\":{},\"villainRange\":\"{}\"{}}}",
+            mc_equity, mc_equity * 100.0, win_pct, tie_pct, total_wins, total_ties, losses, total_count, seed, elapsed_ms, num_threads, villain_range_str, rank_info
         );
     } else {
         eprintln!();
@@ -1454,21 +3781,25 @@ fn run_equity(args: &[String]) {
         eprintln!("  Trials:  {} (W:{} T:{} L:{})", total_count, total_wins, total_ties, losses);
         eprintln!("  Seed:    {}", seed);
         eprintln!("  Time:    {:.1}s", elapsed);
+        if show_categories {
+            eprintln!();
+            eprintln!("  {:<16}  {:>8}  {:>8}  {:>8}  {:>8}", "Category", "Hero%", "Win%", "Villain%", "Win%");
+            eprintln!("  ────────────────  ────────  ────────  ────────  ────────");
+            let win_rate = |c: &[f64; 3]| if c[0] > 0.0 { format!("{:.2}%", c[1] / c[0] * 100.0) } else { "-".into() };
+            for (cat, name) in CATEGORY_NAMES.iter().enumerate() {
+                let (hero, villain) = (&tally.hero[cat], &tally.villain[cat]);
+                if hero[0] == 0.0 && villain[0] == 0.0 { continue; }
+                eprintln!("  {:<16}  {:>7.2}%  {:>8}  {:>7.2}%  {:>8}", name,
+                    hero[0] / weighted_total * 100.0, win_rate(hero),
+                    villain[0] / weighted_total * 100.0, win_rate(villain));
+            }
+        }
     }
 }
 
 fn run_build_rank_index(args: &[String]) {
     let bin_file = parse_flag(args, "--bin")
         .unwrap_or_else(|| "public/plo5_rankings_prod.bin".into());
-    let out_file = parse_flag(args, "--out")
-        .unwrap_or_else(|| "public/rank_index_all_2598960.u32".into());
-
-    eprintln!("╔══════════════════════════════════════════════╗");
-    eprintln!("║  Build Rank Index from Production Binary      ║");
-    eprintln!("╚══════════════════════════════════════════════╝");
-    eprintln!();
-    eprintln!("  Source: {}", bin_file);
-    eprintln!("  Output: {}", out_file);
 
     let data = std::fs::read(&bin_file).expect("Cannot read production binary");
     if data.len() < 68 {
@@ -1478,6 +3809,17 @@ fn run_build_rank_index(args: &[String]) {
     if magic != "PLO5" {
         panic!("Invalid magic: {} (expected PLO5)", magic);
     }
+    let opponents = bin_opponents(&data);
+    let out_file = parse_flag(args, "--out")
+        .unwrap_or_else(|| multiway_path("public/rank_index_all_2598960.u32", opponents));
+
+    eprintln!("╔══════════════════════════════════════════════╗");
+    eprintln!("║  Build Rank Index from Production Binary      ║");
+    eprintln!("╚══════════════════════════════════════════════╝");
+    eprintln!();
+    eprintln!("  Source: {}", bin_file);
+    eprintln!("  Output: {}", out_file);
+    eprintln!("  Opponents: {}", opponents);
 
     let num_hands = u32::from_le_bytes(data[8..12].try_into().unwrap()) as usize;
     let expected_size = 64 + num_hands * 20;
@@ -1487,26 +3829,25 @@ fn run_build_rank_index(args: &[String]) {
 
     eprintln!("  Canonical hands in binary: {}", num_hands);
 
-    let mut canonical_equity: HashMap<[u8; 5], f32> = HashMap::with_capacity(num_hands);
+    let canon_index = CanonicalIndex::build();
+    let mut canonical_equity: Vec<Option<f32>> = vec![None; canon_index.len()];
     for i in 0..num_hands {
         let off = 64 + i * 20;
         let cards: [u8; 5] = [data[off], data[off+1], data[off+2], data[off+3], data[off+4]];
         let equity = f32::from_le_bytes(data[off+6..off+10].try_into().unwrap());
-        canonical_equity.insert(cards, equity);
+        canonical_equity[canon_index.id(&cards)] = Some(equity);
     }
 
-    eprintln!("  Loaded {} canonical equities", canonical_equity.len());
+    eprintln!("  Loaded {} canonical equities", canonical_equity.iter().filter(|e| e.is_some()).count());
 
     let total: u32 = 2598960;
     let mut equities: Vec<f32> = vec![0.0; total as usize];
     let mut missing = 0u32;
 
     eprintln!("  Mapping all 2,598,960 combos to canonical equities...");
-    for idx in 0..total {
-        let hand = index_to_hand(idx);
-        let can = canonicalize(&hand);
-        if let Some(&eq) = canonical_equity.get(&can) {
-            equities[idx as usize] = eq;
+    for (eq_out, &id) in equities.iter_mut().zip(&canon_index.ids) {
+        if let Some(eq) = canonical_equity[id as usize] {
+            *eq_out = eq;
         } else {
             missing += 1;
         }
@@ -1546,6 +3887,7 @@ fn run_build_rank_index(args: &[String]) {
         out.extend_from_slice(&idx.to_le_bytes());
     }
     std::fs::write(&out_file, &out).expect("Failed to write rank index");
+    write_opponents_meta(&out_file, opponents);
     eprintln!("\n  Wrote {} indices ({} bytes) to {}", total, out.len(), out_file);
     eprintln!("  Done!");
 }
@@ -1557,19 +3899,28 @@ fn run_precompute_all(args: &[String]) {
         .and_then(|s| s.parse().ok()).unwrap_or(10);
     let seed: u64 = parse_flag(args, "--seed")
         .and_then(|s| s.parse().ok()).unwrap_or(12345);
-    let threads_str = parse_flag(args, "--threads").unwrap_or_else(|| "auto".into());
+    let opponents = parse_opponents(args, "--opponents").unwrap_or_else(|msg| {
+        eprintln!("{}", msg);
+        std::process::exit(1);
+    });
+    let iterations: u32 = parse_flag(args, "--iterate")
+        .and_then(|s| s.parse().ok()).unwrap_or(0);
+    let iterate_range_str = parse_flag(args, "--iterate-range").unwrap_or_else(|| "20%".into());
+    let iterate_pct = parse_villain_range(&iterate_range_str).unwrap_or_else(|| {
+        eprintln!("Invalid --iterate-range '{}'. Use a percentage like 20%.", iterate_range_str);
+        std::process::exit(1);
+    });
+    let converge: f64 = parse_flag(args, "--converge")
+        .and_then(|s| s.parse().ok()).unwrap_or(0.9999);
     let out_equity = parse_flag(args, "--out-equity")
-        .unwrap_or_else(|| "equity_all_2598960.f32".into());
+        .unwrap_or_else(|| multiway_path("equity_all_2598960.f32", opponents));
     let out_rank = parse_flag(args, "--out-rank")
-        .unwrap_or_else(|| "rank_index_all_2598960.u32".into());
+        .unwrap_or_else(|| multiway_path("rank_index_all_2598960.u32", opponents));
 
     let total_hands: u32 = BINOM[52][5];
     assert_eq!(total_hands, 2598960);
 
-    let num_threads: usize = match threads_str.as_str() {
-        "auto" => num_cpus(),
-        s => s.parse().unwrap_or(4),
-    };
+    let num_threads = num_threads();
 
     eprintln!("╔══════════════════════════════════════════════╗");
     eprintln!("║  PLO5 Precompute ALL 2,598,960 hands         ║");
@@ -1578,8 +3929,13 @@ fn run_precompute_all(args: &[String]) {
     eprintln!("  Total hands:        {}", total_hands);
     eprintln!("  Boards/hero:        {}", boards_n);
     eprintln!("  Villain samples:    {}", villain_samples);
+    eprintln!("  Opponents:          {}", opponents);
     eprintln!("  Seed:               {}", seed);
     eprintln!("  Threads:            {}", num_threads);
+    if iterations > 0 {
+        eprintln!("  Iterations:         up to {} vs top {:.1}%, stop at Spearman >= {}",
+            iterations, iterate_pct, converge);
+    }
     eprintln!("  Output equity:      {}", out_equity);
     eprintln!("  Output rank:        {}", out_rank);
     eprintln!();
@@ -1604,121 +3960,605 @@ fn run_precompute_all(args: &[String]) {
     eprintln!("       {} showdowns/hero × {} heroes = {:.2}B total",
         evals_per_hero, total_hands, total_evals as f64 / 1e9);
 
-    let equities = vec![0.0f64; total_hands as usize];
-    let equity_ptr = equities.as_ptr() as usize;
-
-    let progress = AtomicU64::new(0);
     let t2 = Instant::now();
-    let chunk_size = (total_hands as usize + num_threads - 1) / num_threads;
-
-    thread::scope(|s| {
-        for t in 0..num_threads {
-            let table_ref = &table;
-            let progress_ref = &progress;
-            let eq_ptr = equity_ptr;
-            s.spawn(move || {
-                let start = t * chunk_size;
-                let end = ((t + 1) * chunk_size).min(total_hands as usize);
-                let eq_slice = unsafe {
-                    std::slice::from_raw_parts_mut(eq_ptr as *mut f64, total_hands as usize)
-                };
-                let mut rng = Xorshift64::new(mix_seed(seed.wrapping_add(t as u64)));
+    let mut equities = all_hands_equity(boards_n, villain_samples, opponents, &[], seed, &table);
+    let compute_time = t2.elapsed().as_secs_f64();
+    eprintln!("       Done in {:.1}s ({:.0} hands/s)", compute_time, total_hands as f64 / compute_time);
+    let mut order = rank_order(&equities);
 
-                for idx in start..end {
-                    let hero = index_to_hand(idx as u32);
-                    let hero_bm = card_bitmap(&hero);
-                    let hero_2s = two_card_subsets(&hero);
+    if iterations > 0 {
+        let top_count = (((iterate_pct / 100.0) * total_hands as f64).floor() as usize)
+            .clamp(5 * opponents as usize, total_hands as usize);
+        eprintln!();
+        eprintln!("       Re-ranking vs the top {:.1}% ({} hands) of the previous ranking", iterate_pct, top_count);
+        let iter_equity = with_suffix(&out_equity, "_iter0");
+        let iter_rank = with_suffix(&out_rank, "_iter0");
+        write_all_hands(&equities, &order, opponents, &iter_equity, &iter_rank);
+        let mut summary = vec![(0u32, None, None, iter_rank)];
+
+        for iteration in 1..=iterations {
+            let range: Vec<[u8; 5]> = order[..top_count].iter().map(|&idx| index_to_hand(idx)).collect();
+            eprintln!();
+            eprintln!("       Iteration {}/{}: villains from the previous top {}", iteration, iterations, top_count);
+            let t_iter = Instant::now();
+            equities = all_hands_equity(boards_n, villain_samples, opponents, &range, seed, &table);
+            let next_order = rank_order(&equities);
+            let rho = spearman(&order, &next_order);
+            let overlap = range_overlap(&order[..top_count], &next_order[..top_count]);
+            eprintln!("       Done in {:.1}s — Spearman vs previous {:.6}, top-{:.1}% overlap {:.2}%",
+                t_iter.elapsed().as_secs_f64(), rho, iterate_pct, overlap * 100.0);
+            order = next_order;
+
+            let iter_equity = with_suffix(&out_equity, &format!("_iter{}", iteration));
+            let iter_rank = with_suffix(&out_rank, &format!("_iter{}", iteration));
+            write_all_hands(&equities, &order, opponents, &iter_equity, &iter_rank);
+            summary.push((iteration, Some(rho), Some(overlap), iter_rank));
+            if rho >= converge {
+                eprintln!("       Converged: Spearman {:.6} >= {}", rho, converge);
+                break;
+            }
+        }
 
-                    let remaining: Vec<u8> = (0..52u8)
-                        .filter(|c| hero_bm & (1u64 << (*c as u64)) == 0)
-                        .collect();
+        eprintln!();
+        eprintln!("  Iteration  Spearman    Top overlap  Rank index");
+        for (iteration, rho, overlap, path) in &summary {
+            let rho = rho.map_or("—".to_string(), |r| format!("{:.6}", r));
+            let overlap = overlap.map_or("—".to_string(), |o| format!("{:.2}%", o * 100.0));
+            eprintln!("  {:>9}  {:>8}  {:>11}  {}", iteration, rho, overlap, path);
+        }
+    }
 
-                    let mut wins = 0.0f64;
-                    let mut total = 0u64;
+    eprintln!();
+    eprintln!("[4/4] Writing output files...");
+    write_all_hands(&equities, &order, opponents, &out_equity, &out_rank);
 
-                    for _ in 0..boards_n {
-                        let sampled = sample_n(&remaining, 10, &mut rng);
-                        let mut board = [sampled[0], sampled[1], sampled[2], sampled[3], sampled[4]];
-                        board.sort();
-                        let board_bm = card_bitmap(&board);
-                        let board_3s = three_card_subsets(&board);
-                        let hero_rank = eval_best(&hero_2s, &board_3s, table_ref);
+    let total_time = t0.elapsed().as_secs_f64();
+    eprintln!();
+    eprintln!("  Total time: {:.1}s ({:.1} min)", total_time, total_time / 60.0);
+    eprintln!("  Done!");
+}
 
-                        let pool: Vec<u8> = remaining.iter()
-                            .filter(|&&c| board_bm & (1u64 << (c as u64)) == 0)
-                            .copied()
-                            .collect();
+/// `path` with `suffix` inserted before its extension.
+fn with_suffix(path: &str, suffix: &str) -> String {
+    match path.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !ext.contains('/') => format!("{}{}.{}", stem, suffix, ext),
+        _ => format!("{}{}", path, suffix),
+    }
+}
+
+/// Equity of every one of the 2,598,960 hands, indexed by `comb_index`.
+/// Villains are random hands when `range` is empty, otherwise drawn from
+/// `range` with the same rejection of blocked combos that `run_equity` uses.
+fn all_hands_equity(boards_n: u32, villain_samples: u32, opponents: u32, range: &[[u8; 5]],
+                    seed: u64, table: &[u16]) -> Vec<f64> {
+    let total_hands: u32 = BINOM[52][5];
+    let num_threads = num_threads();
+
+    let progress = AtomicU64::new(0);
+    let t2 = Instant::now();
+    let chunk_size = (total_hands as usize).div_ceil(num_threads);
+    let thread_rngs = EngineRng::streams(seed, num_threads, |t| mix_seed(seed.wrapping_add(t as u64)));
+
+    let chunks = parallel_map(num_threads, |t| {
+        let mut rng = thread_rngs[t].clone();
+        let start = t * chunk_size;
+        let end = ((t + 1) * chunk_size).min(total_hands as usize);
+        let mut chunk = Vec::with_capacity(end - start);
+
+        for idx in start..end {
+            let hero = index_to_hand(idx as u32);
+            let hero_bm = card_bitmap(&hero);
+            let hero_2s = two_card_subsets(&hero);
+
+            let remaining: Vec<u8> = (0..52u8)
+                .filter(|c| hero_bm & (1u64 << (*c as u64)) == 0)
+                .collect();
 
-                        for v in 0..villain_samples {
-                            let _ = v;
-                            let villain = sample_villain(&pool, &mut rng);
-                            let villain_2s = two_card_subsets(&villain);
-                            let villain_rank = eval_best(&villain_2s, &board_3s, table_ref);
-                            if hero_rank < villain_rank { wins += 1.0; }
-                            else if hero_rank == villain_rank { wins += 0.5; }
+            let mut wins = 0.0f64;
+            let mut total = 0u64;
+
+            for _ in 0..boards_n {
+                let sampled = sample_n(&remaining, 10, &mut rng);
+                let mut board = [sampled[0], sampled[1], sampled[2], sampled[3], sampled[4]];
+                board.sort();
+                let board_bm = card_bitmap(&board);
+                let board_3s = three_card_subsets(&board);
+                let hero_rank = eval_best(&hero_2s, &board_3s, table);
+                let rank_of = |villain: &[u8; 5]| eval_best(&two_card_subsets(villain), &board_3s, table);
+
+                if !range.is_empty() {
+                    for _ in 0..villain_samples {
+                        if let Some(share) = range_share(hero_rank, hero_bm | board_bm, range,
+                                                         opponents, &mut rng, rank_of) {
+                            wins += share;
                             total += 1;
                         }
                     }
+                    continue;
+                }
 
-                    eq_slice[idx] = if total > 0 { wins / total as f64 } else { 0.5 };
+                let pool: Vec<u8> = remaining.iter()
+                    .filter(|&&c| board_bm & (1u64 << (c as u64)) == 0)
+                    .copied()
+                    .collect();
 
-                    let done = progress_ref.fetch_add(1, Ordering::Relaxed) + 1;
-                    if done % 50000 == 0 || done == total_hands as u64 {
-                        let elapsed = t2.elapsed().as_secs_f64();
-                        let rate = done as f64 / elapsed;
-                        let eta = (total_hands as u64 - done) as f64 / rate;
-                        eprint!("\r       {}/{} ({:.1}%) — {:.0} hands/s — ETA {:.0}s   ",
-                            done, total_hands, done as f64 / total_hands as f64 * 100.0, rate, eta);
-                    }
+                for _ in 0..villain_samples {
+                    wins += multiway_share(hero_rank, &pool, opponents, &mut rng, rank_of);
+                    total += 1;
                 }
-            });
+            }
+
+            chunk.push(if total > 0 { wins / total as f64 } else { 0.5 });
+
+            let done = progress.fetch_add(1, Ordering::Relaxed) + 1;
+            if done.is_multiple_of(50000) || done == total_hands as u64 {
+                let elapsed = t2.elapsed().as_secs_f64();
+                let rate = done as f64 / elapsed;
+                let eta = (total_hands as u64 - done) as f64 / rate;
+                eprint!("\r       {}/{} ({:.1}%) — {:.0} hands/s — ETA {:.0}s   ",
+                    done, total_hands, done as f64 / total_hands as f64 * 100.0, rate, eta);
+            }
         }
+        chunk
     });
     eprintln!();
-    let compute_time = t2.elapsed().as_secs_f64();
-    eprintln!("       Done in {:.1}s ({:.0} hands/s)", compute_time, total_hands as f64 / compute_time);
+    chunks.concat()
+}
 
-    eprintln!();
-    eprintln!("[4/4] Writing output files...");
+/// Hero's pot share against `opponents` hands drawn from `range`, each
+/// rejected while it collides with `used_bm` or an earlier opponent (200
+/// tries, as in `run_equity`). `None` when the range is too blocked to deal.
+fn range_share<R: RandomSource>(hero_rank: u16, used_bm: u64, range: &[[u8; 5]], opponents: u32, rng: &mut R,
+                                rank_of: impl Fn(&[u8; 5]) -> u16) -> Option<f64> {
+    let mut used_bm = used_bm;
+    let mut split = 1u32;
+    let mut beaten = false;
+    for _ in 0..opponents {
+        let villain = (0..200)
+            .map(|_| range[rng.gen_range(range.len())])
+            .find(|cand| card_bitmap(cand) & used_bm == 0)?;
+        used_bm |= card_bitmap(&villain);
+        let villain_rank = rank_of(&villain);
+        if villain_rank < hero_rank {
+            beaten = true;
+        } else if villain_rank == hero_rank {
+            split += 1;
+        }
+    }
+    Some(if beaten { 0.0 } else { 1.0 / split as f64 })
+}
+
+/// Hand indices from best to worst equity.
+fn rank_order(equities: &[f64]) -> Vec<u32> {
+    let mut indices: Vec<u32> = (0..equities.len() as u32).collect();
+    indices.sort_by(|&a, &b| {
+        equities[b as usize]
+            .partial_cmp(&equities[a as usize])
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    indices
+}
 
+/// Spearman rank correlation between two orderings of the same hands.
+fn spearman(a: &[u32], b: &[u32]) -> f64 {
+    let n = a.len();
+    let mut position = vec![0u32; n];
+    for (pos, &idx) in a.iter().enumerate() {
+        position[idx as usize] = pos as u32;
+    }
+    let d2: f64 = b.iter().enumerate()
+        .map(|(pos, &idx)| {
+            let d = pos as f64 - position[idx as usize] as f64;
+            d * d
+        })
+        .sum();
+    let n = n as f64;
+    1.0 - 6.0 * d2 / (n * (n * n - 1.0))
+}
+
+/// Fraction of the hands in `a` that also appear in `b` (same length).
+fn range_overlap(a: &[u32], b: &[u32]) -> f64 {
+    let members: HashSet<u32> = a.iter().copied().collect();
+    b.iter().filter(|idx| members.contains(idx)).count() as f64 / a.len().max(1) as f64
+}
+
+/// Writes the `.f32` equity file and the `.u32` rank index for all hands,
+/// each with its opponents sidecar.
+fn write_all_hands(equities: &[f64], order: &[u32], opponents: u32, out_equity: &str, out_rank: &str) {
     {
-        let f = File::create(&out_equity).expect("Cannot create equity output file");
+        let f = File::create(out_equity).expect("Cannot create equity output file");
         let mut w = BufWriter::new(f);
-        for &eq in &equities {
+        for &eq in equities {
             w.write_all(&(eq as f32).to_le_bytes()).unwrap();
         }
         w.flush().unwrap();
-        let size = total_hands as u64 * 4;
+        let size = equities.len() as u64 * 4;
         eprintln!("       {} — {} bytes ({:.2} MB)", out_equity, size, size as f64 / 1e6);
+        write_opponents_meta(out_equity, opponents);
     }
 
     {
-        let mut indices: Vec<u32> = (0..total_hands).collect();
-        indices.sort_by(|&a, &b| {
-            equities[b as usize]
-                .partial_cmp(&equities[a as usize])
-                .unwrap_or(std::cmp::Ordering::Equal)
-        });
-
-        let f = File::create(&out_rank).expect("Cannot create rank output file");
+        let f = File::create(out_rank).expect("Cannot create rank output file");
         let mut w = BufWriter::new(f);
-        for &idx in &indices {
+        for &idx in order {
             w.write_all(&idx.to_le_bytes()).unwrap();
         }
         w.flush().unwrap();
-        let size = total_hands as u64 * 4;
+        let size = order.len() as u64 * 4;
         eprintln!("       {} — {} bytes ({:.2} MB)", out_rank, size, size as f64 / 1e6);
+        write_opponents_meta(out_rank, opponents);
     }
+}
 
-    let total_time = t0.elapsed().as_secs_f64();
-    eprintln!();
-    eprintln!("  Total time: {:.1}s ({:.1} min)", total_time, total_time / 60.0);
-    eprintln!("  Done!");
+/// Summary group a next card falls into for the hero, checked in this order.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum NextCardGroup {
+    Flush,
+    Straight,
+    BoardPair,
+    Overcard,
+    Blank,
+}
+
+impl NextCardGroup {
+    const ALL: [NextCardGroup; 5] = [
+        NextCardGroup::Flush,
+        NextCardGroup::Straight,
+        NextCardGroup::BoardPair,
+        NextCardGroup::Overcard,
+        NextCardGroup::Blank,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            NextCardGroup::Flush => "flush",
+            NextCardGroup::Straight => "straight",
+            NextCardGroup::BoardPair => "board_pair",
+            NextCardGroup::Overcard => "overcard",
+            NextCardGroup::Blank => "blank",
+        }
+    }
+}
+
+/// Best `hand_sort_key` category (0 = straight flush .. 8 = high card) the
+/// hand makes with exactly two hole cards and three of the 3-5 board cards.
+fn best_category(hand: &[u8; 5], board: &[u8]) -> u8 {
+    let mut best = u8::MAX;
+    let mut five = [0u8; 5];
+    for h in &two_card_subsets(hand) {
+        for i in 0..board.len() {
+            for j in (i + 1)..board.len() {
+                for k in (j + 1)..board.len() {
+                    five[..2].copy_from_slice(h);
+                    five[2..].copy_from_slice(&[board[i], board[j], board[k]]);
+                    let cat = (hand_sort_key(&five) / 14u64.pow(5)) as u8;
+                    best = best.min(cat);
+                }
+            }
+        }
+    }
+    best
+}
+
+/// Groups `card` by what it does for the hero on `board`: flush and straight
+/// are cards that move hero's own best hand up to (at least) that category,
+/// so a draw that only arrives for the board does not count. Other cards
+/// pair the board, are overcards, or are blanks.
+fn next_card_group(hand: &[u8; 5], board: &[u8], card: u8) -> NextCardGroup {
+    let mut next_board = board.to_vec();
+    next_board.push(card);
+    let before = best_category(hand, board);
+    let after = best_category(hand, &next_board);
+
+    if (after == 3 && before > 3) || (after == 0 && before > 0) {
+        return NextCardGroup::Flush;
+    }
+    if after == 4 && before > 4 {
+        return NextCardGroup::Straight;
+    }
+    let rank_mask = board.iter().fold(0u16, |m, &c| m | (1 << (c % 13)));
+    if rank_mask & (1 << (card % 13)) != 0 {
+        return NextCardGroup::BoardPair;
+    }
+    if board.iter().all(|&c| c % 13 < card % 13) {
+        return NextCardGroup::Overcard;
+    }
+    NextCardGroup::Blank
+}
+
+/// Hero's exact showdown result against every 5-card holding from `pool` on a
+/// complete board, as (wins + 0.5 * ties, holdings). Pair ranks come from the
+/// prepared board, so a holding costs ten reads instead of a full `eval_best`.
+fn exact_villain_outcomes(hero_rank: u16, pool: &[u8], board: &PreparedBoard) -> (f64, u64) {
+    let n = pool.len();
+    let mut pair_rank = vec![u16::MAX; n * n];
+    for a in 0..n {
+        for b in (a + 1)..n {
+            pair_rank[a * n + b] = board.pair(pool[a], pool[b]);
+        }
+    }
+    let r = |i: usize, j: usize| pair_rank[i * n + j];
+
+    let (mut wins, mut ties, mut total) = (0u64, 0u64, 0u64);
+    for a in 0..n {
+        for b in (a + 1)..n {
+            let m2 = r(a, b);
+            for c in (b + 1)..n {
+                let m3 = m2.min(r(a, c)).min(r(b, c));
+                for d in (c + 1)..n {
+                    let m4 = m3.min(r(a, d)).min(r(b, d)).min(r(c, d));
+                    for e in (d + 1)..n {
+                        let m5 = m4.min(r(a, e)).min(r(b, e)).min(r(c, e)).min(r(d, e));
+                        if hero_rank < m5 { wins += 1; }
+                        else if hero_rank == m5 { ties += 1; }
+                        total += 1;
+                    }
+                }
+            }
+        }
+    }
+    (wins as f64 + 0.5 * ties as f64, total)
+}
+
+/// True when every 5-card holding from `pool` beats hero on a complete board.
+/// A holding fails to beat hero only if all ten of its pairs rank no better
+/// than hero, so this searches for five cards that are pairwise "safe" and
+/// stops at the first one found.
+fn drawing_dead(hero_rank: u16, pool: &[u8], board: &PreparedBoard) -> bool {
+    let n = pool.len();
+    let safe: Vec<u64> = (0..n).map(|a| {
+        (0..n).filter(|&b| b != a && board.pair(pool[a], pool[b]) >= hero_rank)
+            .fold(0u64, |m, b| m | (1u64 << b))
+    }).collect();
+    fn extend(safe: &[u64], candidates: u64, depth: usize) -> bool {
+        if depth == 5 { return true; }
+        if (candidates.count_ones() as usize) < 5 - depth { return false; }
+        let mut rest = candidates;
+        while rest != 0 {
+            let a = rest.trailing_zeros() as usize;
+            rest &= rest - 1;
+            if extend(safe, rest & safe[a], depth + 1) { return true; }
+        }
+        false
+    }
+    let all = if n == 64 { u64::MAX } else { (1u64 << n) - 1 };
+    !extend(&safe, all, 0)
+}
+
+/// Exact heads-up outcomes against a random hand on one river board, for any
+/// hero. Every 5-card holding from the 47 unseen cards is ranked once, and
+/// `sorted[k]` keeps, for each k-subset of those cards (colex order, fixed
+/// stride), the sorted ranks of the holdings that contain it. The holdings
+/// that avoid hero's cards are then counted by inclusion-exclusion over the
+/// 32 subsets of hero's hand, so no villain is ever sampled.
+struct RiverVillainCounts {
+    /// Position of each card among the unseen 47; `u8::MAX` for board cards.
+    position: [u8; 52],
+    sorted: [Vec<u16>; 6],
+}
+
+impl RiverVillainCounts {
+    fn stride(k: usize) -> usize {
+        BINOM[47 - k][5 - k] as usize
+    }
+
+    /// Colex slot of every subset of the sorted positions `p`, by bit mask.
+    fn subset_slots(p: &[usize; 5]) -> [usize; 32] {
+        let mut slots = [0usize; 32];
+        for mask in 1..32usize {
+            let top = 31 - (mask as u32).leading_zeros() as usize;
+            slots[mask] = slots[mask & !(1 << top)] + BINOM[p[top]][mask.count_ones() as usize] as usize;
+        }
+        slots
+    }
+
+    /// Colex slot of the subset of `p` picked by `mask`, as in `subset_slots`.
+    fn subset_slot(p: &[usize; 5], mask: u32) -> usize {
+        (0..5).filter(|&i| mask & (1 << i) != 0)
+            .enumerate()
+            .map(|(j, i)| BINOM[p[i]][j + 1] as usize)
+            .sum()
+    }
+
+    fn new(board_cards: &[u8; 5], board: &PreparedBoard) -> Self {
+        let mut position = [u8::MAX; 52];
+        let unseen: Vec<u8> = (0..52u8).filter(|c| !board_cards.contains(c)).collect();
+        for (i, &c) in unseen.iter().enumerate() {
+            position[c as usize] = i as u8;
+        }
+
+        // One ranking task per lowest position; concatenated in task order
+        // they are the holdings in lexicographic order.
+        let mut holdings: Vec<(u16, [usize; 5])> = parallel_map(47 - 4, |first| {
+            let mut ranked = Vec::with_capacity(BINOM[46 - first][4] as usize);
+            let mut p = [first, first + 1, first + 2, first + 3, first + 4];
+            'enumerate: loop {
+                ranked.push((board.best_rank(&p.map(|i| unseen[i])), p));
+                let mut i = 5;
+                loop {
+                    if i == 1 {
+                        break 'enumerate;
+                    }
+                    i -= 1;
+                    if p[i] < 47 - 5 + i {
+                        break;
+                    }
+                }
+                p[i] += 1;
+                for j in (i + 1)..5 {
+                    p[j] = p[j - 1] + 1;
+                }
+            }
+            ranked
+        }).concat();
+
+        // Filling in rank order leaves every subset's run already sorted.
+        // Each subset size has its own table, so each is filled by its own task.
+        holdings.sort_unstable_by_key(|&(rank, _)| rank);
+        let sorted = parallel_map(6, |k| {
+            let stride = Self::stride(k);
+            let mut sorted = vec![0u16; BINOM[47][k] as usize * stride];
+            let mut fill = vec![0u32; BINOM[47][k] as usize];
+            let masks: Vec<u32> = (0..32u32).filter(|m| m.count_ones() as usize == k).collect();
+            for (rank, p) in &holdings {
+                for &mask in &masks {
+                    let slot = Self::subset_slot(p, mask);
+                    sorted[slot * stride + fill[slot] as usize] = *rank;
+                    fill[slot] += 1;
+                }
+            }
+            sorted
+        });
+        RiverVillainCounts { position, sorted: sorted.try_into().expect("one table per subset size") }
+    }
+
+    /// (wins, ties, total) over all C(42,5) villain holdings for a hero that
+    /// does not touch the board.
+    fn outcomes(&self, hero: &[u8; 5], hero_rank: u16) -> (u64, u64, u64) {
+        let mut pos = hero.map(|c| self.position[c as usize] as usize);
+        pos.sort_unstable();
+        let (mut better, mut better_or_tied) = (0i64, 0i64);
+        for (mask, &slot) in Self::subset_slots(&pos).iter().enumerate() {
+            let k = mask.count_ones() as usize;
+            let stride = Self::stride(k);
+            let chunk = &self.sorted[k][slot * stride..(slot + 1) * stride];
+            let sign = if k.is_multiple_of(2) { 1 } else { -1 };
+            better += sign * chunk.partition_point(|&r| r < hero_rank) as i64;
+            better_or_tied += sign * chunk.partition_point(|&r| r <= hero_rank) as i64;
+        }
+        let total = BINOM[42][5] as u64;
+        (total - better_or_tied as u64, (better_or_tied - better) as u64, total)
+    }
+}
+
+/// Hero hand, board so far and villain model shared by every breakdown card.
+struct BreakdownSetup<'a> {
+    hand: [u8; 5],
+    /// Flop (the next card is the turn) or turn (the next card is the river).
+    board_cards: &'a [u8],
+    /// Hand, board and dead cards.
+    excluded_bm: u64,
+    /// Common random numbers: an optional river card and a villain seed each.
+    scenarios: &'a [(Vec<u8>, Vec<u64>)],
+    /// Villain range pool; empty for uniform villains, which are enumerated
+    /// exactly on the river when `exact_river` is set and sampled otherwise.
+    rank_pool: &'a [[u8; 5]],
+    exact_river: bool,
+    table: &'a [u16],
+}
+
+/// Hero equity once `next_card` is dealt, as (equity, showdowns).
+fn breakdown_card(setup: &BreakdownSetup, next_card: u8) -> (f64, u64) {
+    let next_bm = 1u64 << (next_card as u64);
+    let mut full_board_base: Vec<u8> = setup.board_cards.to_vec();
+    full_board_base.push(next_card);
+
+    let combined_bm_base = card_bitmap(&setup.hand) | card_bitmap(setup.board_cards) | next_bm;
+
+    let mut wins = 0.0f64;
+    let mut count = 0u64;
+
+    if setup.board_cards.len() == 3 {
+        let hero_2s = two_card_subsets(&setup.hand);
+        for sc in setup.scenarios {
+            let river_card = sc.0[0];
+            let river_bm = 1u64 << (river_card as u64);
+            if river_bm & combined_bm_base != 0 { continue; }
+            let combined_bm = combined_bm_base | river_bm;
+
+            let mut full_board = full_board_base.clone();
+            full_board.push(river_card);
+            full_board.sort();
+            let board_3s = three_card_subsets_from_slice(&full_board);
+            let hero_rank = eval_best(&hero_2s, &board_3s, setup.table);
+
+            let vseed = sc.1[0];
+            if !setup.rank_pool.is_empty() {
+                let pool_len = setup.rank_pool.len();
+                let mut vrng = EngineRng::seeded(mix_seed(vseed));
+                let mut found = false;
+                let mut villain_arr = [0u8; 5];
+                for _ in 0..200 {
+                    let vi = vrng.gen_range(pool_len);
+                    let cand = setup.rank_pool[vi];
+                    let cand_bm = card_bitmap(&cand);
+                    if cand_bm & combined_bm != 0 { continue; }
+                    villain_arr = cand;
+                    found = true;
+                    break;
+                }
+                if !found { continue; }
+                let villain_2s = two_card_subsets(&villain_arr);
+                let villain_rank = eval_best(&villain_2s, &board_3s, setup.table);
+                if hero_rank < villain_rank { wins += 1.0; }
+                else if hero_rank == villain_rank { wins += 0.5; }
+                count += 1;
+            } else {
+                let pool: Vec<u8> = (0..52u8)
+                    .filter(|c| combined_bm & (1u64 << (*c as u64)) == 0)
+                    .collect();
+                let mut vrng = EngineRng::seeded(mix_seed(vseed));
+                let villain = sample_villain(&pool, &mut vrng);
+                let villain_2s = two_card_subsets(&villain);
+                let villain_rank = eval_best(&villain_2s, &board_3s, setup.table);
+                if hero_rank < villain_rank { wins += 1.0; }
+                else if hero_rank == villain_rank { wins += 0.5; }
+                count += 1;
+            }
+        }
+    } else {
+        full_board_base.sort();
+        let prepared = PreparedBoard::new(&full_board_base[..].try_into().unwrap(), setup.table);
+        let hero_rank = prepared.best_rank(&setup.hand);
+
+        if setup.rank_pool.is_empty() && setup.exact_river {
+            let pool: Vec<u8> = (0..52u8)
+                .filter(|&c| (setup.excluded_bm | next_bm) & (1u64 << c) == 0)
+                .collect();
+            (wins, count) = exact_villain_outcomes(hero_rank, &pool, &prepared);
+        } else if setup.rank_pool.is_empty() {
+            let pool: Vec<u8> = (0..52u8)
+                .filter(|c| combined_bm_base & (1u64 << (*c as u64)) == 0)
+                .collect();
+            for sc in setup.scenarios {
+                let mut vrng = EngineRng::seeded(mix_seed(sc.1[0]));
+                let villain = sample_villain(&pool, &mut vrng);
+                let villain_rank = prepared.best_rank(&villain);
+                if hero_rank < villain_rank { wins += 1.0; }
+                else if hero_rank == villain_rank { wins += 0.5; }
+                count += 1;
+            }
+        } else {
+            let pool_len = setup.rank_pool.len();
+            for sc in setup.scenarios {
+                let mut vrng = EngineRng::seeded(mix_seed(sc.1[0]));
+                let mut found = false;
+                let mut villain_arr = [0u8; 5];
+                for _ in 0..200 {
+                    let vi = vrng.gen_range(pool_len);
+                    let cand = setup.rank_pool[vi];
+                    let cand_bm = card_bitmap(&cand);
+                    if cand_bm & combined_bm_base != 0 { continue; }
+                    villain_arr = cand;
+                    found = true;
+                    break;
+                }
+                if !found { continue; }
+                let villain_rank = prepared.best_rank(&villain_arr);
+                if hero_rank < villain_rank { wins += 1.0; }
+                else if hero_rank == villain_rank { wins += 0.5; }
+                count += 1;
+            }
+        }
+    }
+
+    let eq = if count > 0 { wins / count as f64 } else { 0.5 };
+    (eq, count)
 }
 
 fn run_breakdown(args: &[String]) {
     let hand_str = parse_flag(args, "--hand").unwrap_or_else(|| {
-        eprintln!("Usage: plo5_ranker breakdown --hand <hand> --board <3or4cards> [--dead <cards>] [--trials-budget N] [--seed S] [--villain-range 100%|N%] [--rank-file path] [--json]");
+        eprintln!("Usage: plo5_ranker breakdown --hand <hand> --board <3or4cards> [--dead <cards>] [--trials-budget N] [--seed S] [--villain-range 100%|N%] [--rank-file path] [--matrix] [--threads N] [--json]");
         std::process::exit(1);
     });
     let trials_budget: u64 = parse_flag(args, "--trials-budget")
@@ -1729,266 +4569,1324 @@ fn run_breakdown(args: &[String]) {
     let board_str = parse_flag(args, "--board").unwrap_or_default();
     let dead_str = parse_flag(args, "--dead").unwrap_or_default();
     let villain_range_str = parse_flag(args, "--villain-range").unwrap_or_else(|| "100%".into());
-    let rank_file = parse_flag(args, "--rank-file").unwrap_or_else(|| "public/rank_index_all_2598960.u32".into());
+    let (rank_file, _) = range_files(args).unwrap_or_else(|msg| fail(json_output, msg));
 
     let villain_pct = parse_villain_range(&villain_range_str).unwrap_or_else(|| {
-        if json_output {
-            println!("{{\"ok\":false,\"error\":\"Invalid villain range: '{}'. Use N% (e.g. 10%, 20%, 100%)\"}}", villain_range_str);
-            std::process::exit(0);
-        }
-        eprintln!("Invalid villain range: '{}'. Use N% (e.g. 10%, 20%, 100%)", villain_range_str);
-        std::process::exit(1);
+        fail(json_output, format!("Invalid villain range: '{}'. Use N% (e.g. 10%, 20%, 100%)", villain_range_str))
     });
     let is_range_restricted = villain_pct < 100.0;
 
-    let hand = parse_hand(&hand_str).unwrap_or_else(|| {
+    let hand = parse_hand(&hand_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse hand: {} ({})", hand_str, e)));
+    let board_cards = parse_cards(&board_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse board: {} ({})", board_str, e)));
+    let dead_cards = parse_cards(&dead_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse dead cards: {} ({})", dead_str, e)));
+
+    if args.iter().any(|a| a == "--matrix") && board_cards.len() != 3 {
+        fail(json_output, format!("Breakdown matrix requires a 3-card flop, got {}", board_cards.len()));
+    }
+
+    if board_cards.len() != 3 && board_cards.len() != 4 {
+        fail(json_output, format!("Breakdown requires board of 3 (flop) or 4 (turn) cards, got {}", board_cards.len()));
+    }
+
+    let mut excluded: Vec<u8> = Vec::new();
+    for &c in hand.iter() { excluded.push(c); }
+    for &c in &board_cards { excluded.push(c); }
+    for &c in &dead_cards { excluded.push(c); }
+    excluded.sort();
+    excluded.dedup();
+    if excluded.len() != hand.len() + board_cards.len() + dead_cards.len() {
+        fail(json_output, "Duplicate cards found among hand, board, and dead cards".into());
+    }
+
+    let excluded_bm = card_bitmap(&excluded);
+    let candidate_cards: Vec<u8> = (0..52u8)
+        .filter(|c| excluded_bm & (1u64 << (*c as u64)) == 0)
+        .collect();
+    let num_candidates = candidate_cards.len();
+    let is_turn_breakdown = board_cards.len() == 3;
+    // With the board complete after the river card, a random villain can be
+    // enumerated outright instead of sampled. `--rng legacy` keeps the old
+    // per-card sampling, so its output matches older engines.
+    let legacy_rng = LEGACY_RNG.load(Ordering::Relaxed);
+    let exact_river = !is_turn_breakdown && !is_range_restricted && !legacy_rng;
+
+    let trials_per_card = (trials_budget / num_candidates as u64).max(100);
+    let matrix_mode = args.iter().any(|a| a == "--matrix");
+    let trials_per_runout = (trials_budget / (num_candidates * (num_candidates - 1) / 2) as u64).max(100);
+
+    // Next cards that are suit-isomorphic given hand, board and dead cards
+    // share one computation; the first card of each class is its representative.
+    // Legacy output samples every card on its own.
+    let class_fixed: [&[u8]; 3] = [&hand, &board_cards, &dead_cards];
+    let mut class_reps: Vec<u8> = Vec::new();
+    let mut class_of_card = [usize::MAX; 52];
+    let mut class_by_key: HashMap<usize, usize> = HashMap::new();
+    for &c in &candidate_cards {
+        let key = if legacy_rng { c as usize } else { board_class_key(&class_fixed, &[c]) };
+        let class = *class_by_key.entry(key).or_insert_with(|| {
+            class_reps.push(c);
+            class_reps.len() - 1
+        });
+        class_of_card[c as usize] = class;
+    }
+    let num_classes = class_reps.len();
+
+    let rank_pool: Vec<[u8; 5]> = if is_range_restricted {
+        let rank_index = load_rank_index(&rank_file);
+        let top_count = ((villain_pct / 100.0) * 2598960.0).floor() as usize;
+        let top_count = top_count.clamp(1, 2598960);
+        rank_index[..top_count].iter().map(|&idx| index_to_hand(idx)).collect()
+    } else {
+        Vec::new()
+    };
+
+    let t0 = Instant::now();
+    if !json_output { eprintln!("[1/2] Initializing eval table..."); }
+    let table = init_eval_table();
+    if !json_output {
+        eprintln!("       Done in {:.2}s", t0.elapsed().as_secs_f64());
+        if matrix_mode {
+            eprintln!("[2/2] Computing turn × river matrix for {} candidate cards ({} trials per runout)...",
+                num_candidates, trials_per_runout);
+        } else if exact_river {
+            eprintln!("[2/2] Computing breakdown for {} candidate cards in {} isomorphism classes (exact villain enumeration)...",
+                num_candidates, num_classes);
+        } else {
+            eprintln!("[2/2] Computing breakdown for {} candidate cards in {} isomorphism classes ({} trials each)...",
+                num_candidates, num_classes, trials_per_card);
+        }
+        eprintln!("       Board: {} cards, next street: {}",
+            board_cards.len(), if is_turn_breakdown { "turn" } else { "river" });
+    }
+
+    let mut crn_scenarios: Vec<(Vec<u8>, Vec<u64>)> = Vec::new();
+    if is_turn_breakdown && !matrix_mode {
+        let mut scenario_rng = EngineRng::seeded(mix_seed(seed.wrapping_add(999)));
+        for _ in 0..trials_per_card {
+            let river_card_idx = scenario_rng.gen_range(52);
+            let river_card = river_card_idx as u8;
+            let villain_seed = scenario_rng.next_u64();
+            crn_scenarios.push((vec![river_card], vec![villain_seed]));
+        }
+    } else {
+        let mut scenario_rng = EngineRng::seeded(mix_seed(seed.wrapping_add(999)));
+        let scenario_count = if matrix_mode { trials_per_runout } else { trials_per_card };
+        for _ in 0..scenario_count {
+            let villain_seed = scenario_rng.next_u64();
+            crn_scenarios.push((vec![], vec![villain_seed]));
+        }
+    }
+
+    if matrix_mode {
+        // Every runout is scored against the same villain seeds, so cells and
+        // rows are directly comparable. Runouts that are suit-isomorphic given
+        // hand, board and dead cards share one computation. Equity is sampled,
+        // but whether hero is drawing dead is decided exactly: no live villain
+        // holding loses to or ties hero on that runout.
+        let runout_fixed: [&[u8]; 3] = [&hand, &board_cards, &dead_cards];
+        let runout_reps = board_classes::<2>(&runout_fixed);
+        let num_runout_classes = runout_reps.len();
+        let class_by_key: HashMap<usize, usize> = runout_reps.iter().enumerate()
+            .map(|(i, (rep, _))| (board_class_key(&runout_fixed, rep), i))
+            .collect();
+        let class_results: Vec<(f64, u8, bool)> = parallel_map(num_runout_classes, |ci| {
+            let runout = &runout_reps[ci].0;
+            let mut full_board: Vec<u8> = board_cards.to_vec();
+            full_board.extend_from_slice(runout);
+            full_board.sort();
+            let prepared = PreparedBoard::new(&full_board[..].try_into().unwrap(), &table);
+            let hero_rank = prepared.best_rank(&hand);
+            let category = rank_to_category(hero_rank);
+            let used_bm = excluded_bm | card_bitmap(runout);
+            let pool: Vec<u8> = (0..52u8).filter(|&c| used_bm & (1u64 << c) == 0).collect();
+
+            let mut wins = 0.0f64;
+            let mut count = 0u64;
+            for sc in crn_scenarios.iter() {
+                let mut vrng = EngineRng::seeded(mix_seed(sc.1[0]));
+                let villain = if is_range_restricted {
+                    let mut found = None;
+                    for _ in 0..200 {
+                        let cand = rank_pool[vrng.gen_range(rank_pool.len())];
+                        if card_bitmap(&cand) & used_bm == 0 {
+                            found = Some(cand);
+                            break;
+                        }
+                    }
+                    match found { Some(v) => v, None => continue }
+                } else {
+                    sample_villain(&pool, &mut vrng)
+                };
+                let villain_rank = prepared.best_rank(&villain);
+                if hero_rank < villain_rank { wins += 1.0; }
+                else if hero_rank == villain_rank { wins += 0.5; }
+                count += 1;
+            }
+            let eq = if count > 0 { wins / count as f64 } else { 0.5 };
+            let dead = if is_range_restricted {
+                rank_pool.iter().all(|v| card_bitmap(v) & used_bm != 0 || prepared.best_rank(v) < hero_rank)
+            } else {
+                drawing_dead(hero_rank, &pool, &prepared)
+            };
+            (eq, category, dead)
+        });
+
+        let n = num_candidates;
+        let mut cell: Vec<Option<(f64, u8, bool)>> = vec![None; n * n];
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let pair = [candidate_cards[i], candidate_cards[j]];
+                let result = class_results[class_by_key[&board_class_key(&runout_fixed, &pair)]];
+                cell[i * n + j] = Some(result);
+                cell[j * n + i] = Some(result);
+            }
+        }
+
+        let num_runouts = n * (n - 1) / 2;
+        let mut category_counts = [0usize; 9];
+        let mut dead_runouts = 0usize;
+        let mut equity_sum = 0.0f64;
+        for i in 0..n {
+            for j in (i + 1)..n {
+                let (eq, cat, dead) = cell[i * n + j].unwrap();
+                category_counts[cat as usize] += 1;
+                if dead { dead_runouts += 1; }
+                equity_sum += eq;
+            }
+        }
+        let turn_rows: Vec<(u8, f64, usize)> = (0..n).map(|i| {
+            let row: Vec<(f64, u8, bool)> = (0..n).filter_map(|j| cell[i * n + j]).collect();
+            let dead = row.iter().filter(|c| c.2).count();
+            (candidate_cards[i], row.iter().map(|c| c.0).sum::<f64>() / row.len() as f64, dead)
+        }).collect();
+        let dead_turns: Vec<u8> = turn_rows.iter()
+            .filter(|r| r.2 == n - 1)
+            .map(|r| r.0)
+            .collect();
+        let avg_equity = equity_sum / num_runouts as f64;
+        let dead_pct = dead_runouts as f64 / num_runouts as f64 * 100.0;
+        let elapsed = t0.elapsed().as_secs_f64();
+        let elapsed_ms = (elapsed * 1000.0) as u64;
+
         if json_output {
-            println!("{{\"ok\":false,\"error\":\"Could not parse hand: {} (need exactly 5 cards)\"}}", hand_str);
-            std::process::exit(0);
+            let names: Vec<String> = candidate_cards.iter().map(|&c| format!("\"{}\"", card_name(c))).collect();
+            print!("{{\"ok\":true,\"street\":\"matrix\",\"cards\":[{}],\"equity\":[", names.join(","));
+            for i in 0..n {
+                if i > 0 { print!(","); }
+                let row: Vec<String> = (0..n).map(|j| match cell[i * n + j] {
+                    Some((eq, _, _)) => format!("{:.4}", eq),
+                    None => "null".into(),
+                }).collect();
+                print!("[{}]", row.join(","));
+            }
+            print!("],\"category\":[");
+            for i in 0..n {
+                if i > 0 { print!(","); }
+                let row: Vec<String> = (0..n).map(|j| match cell[i * n + j] {
+                    Some((_, cat, _)) => cat.to_string(),
+                    None => "null".into(),
+                }).collect();
+                print!("[{}]", row.join(","));
+            }
+            let turn_eq: Vec<String> = turn_rows.iter().map(|r| format!("{:.4}", r.1)).collect();
+            print!("],\"turnEquity\":[{}],\"categories\":[", turn_eq.join(","));
+            for (cat, &count) in category_counts.iter().enumerate() {
+                if cat > 0 { print!(","); }
+                print!("{{\"name\":\"{}\",\"runouts\":{},\"pct\":{:.4}}}",
+                    CATEGORY_NAMES[cat], count, count as f64 / num_runouts as f64 * 100.0);
+            }
+            let dead_names: Vec<String> = dead_turns.iter().map(|&c| format!("\"{}\"", card_name(c))).collect();
+            println!("],\"equityAvg\":{:.6},\"deadRunouts\":{},\"deadPct\":{:.4},\"deadTurns\":[{}],\"totalRunouts\":{},\"numClasses\":{},\"trialsPerRunout\":{},\"seed\":{},\"elapsedMs\":{},\"threadsUsed\":{},\"villainRange\":\"{}\"}}",
+                avg_equity, dead_runouts, dead_pct, dead_names.join(","), num_runouts, num_runout_classes,
+                trials_per_runout, seed, elapsed_ms, num_threads(), villain_range_str);
+        } else {
+            eprintln!();
+            eprintln!("Turn × River matrix: {} runouts in {} isomorphism classes ({} trials each)",
+                num_runouts, num_runout_classes, trials_per_runout);
+            eprintln!("  Average equity:  {:.3}%", avg_equity * 100.0);
+            eprintln!("  Drawing dead:    {} runouts ({:.2}%)", dead_runouts, dead_pct);
+            eprintln!();
+            eprintln!("  {:<16}  {:>8}  {:>8}", "Hero makes", "Runouts", "Pct");
+            eprintln!("  ────────────────  ────────  ────────");
+            for (cat, &count) in category_counts.iter().enumerate() {
+                if count == 0 { continue; }
+                eprintln!("  {:<16}  {:>8}  {:>7.2}%", CATEGORY_NAMES[cat], count, count as f64 / num_runouts as f64 * 100.0);
+            }
+            eprintln!();
+            eprintln!("  {:<6}  {:>10}  {:>11}", "Turn", "Equity%", "Dead rivers");
+            eprintln!("  ──────  ──────────  ───────────");
+            let mut sorted_rows = turn_rows.clone();
+            sorted_rows.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            for (card, eq, dead) in &sorted_rows {
+                eprintln!("  {:<6}  {:>9.3}%  {:>11}", card_name(*card), eq * 100.0, dead);
+            }
+            eprintln!();
+            eprintln!("  Time: {:.1}s, Seed: {}", elapsed, seed);
+        }
+        return;
+    }
+
+    let setup = BreakdownSetup {
+        hand,
+        board_cards: &board_cards,
+        excluded_bm,
+        scenarios: &crn_scenarios,
+        rank_pool: &rank_pool,
+        exact_river,
+        table: &table,
+    };
+    let class_results: Vec<(u8, f64, u64)> = parallel_map(num_classes, |ci| {
+        let (eq, count) = breakdown_card(&setup, class_reps[ci]);
+        (class_reps[ci], eq, count)
+    });
+    let mut all_results: Vec<(u8, f64, u64)> = candidate_cards.iter().map(|&c| {
+        let (_, eq, count) = class_results[class_of_card[c as usize]];
+        (c, eq, count)
+    }).collect();
+    all_results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    let elapsed = t0.elapsed().as_secs_f64();
+    let elapsed_ms = (elapsed * 1000.0) as u64;
+    let total_trials: u64 = all_results.iter().map(|r| r.2).sum();
+
+    let excluded_names: Vec<String> = excluded.iter().map(|&c| card_name(c)).collect();
+
+    let mut card_groups = [NextCardGroup::Blank; 52];
+    for &c in &candidate_cards {
+        card_groups[c as usize] = next_card_group(&hand, &board_cards, c);
+    }
+    let group_rows: Vec<(NextCardGroup, Vec<u8>, f64)> = NextCardGroup::ALL.iter().map(|&g| {
+        let members: Vec<&(u8, f64, u64)> = all_results.iter()
+            .filter(|r| card_groups[r.0 as usize] == g)
+            .collect();
+        let eq = if members.is_empty() { 0.0 } else {
+            members.iter().map(|r| r.1).sum::<f64>() / members.len() as f64
+        };
+        (g, members.iter().map(|r| r.0).collect(), eq)
+    }).collect();
+
+    if json_output {
+        print!("{{\"ok\":true,\"street\":\"{}\",\"items\":[",
+            if is_turn_breakdown { "turn" } else { "river" });
+        for (i, &(card, eq, trials)) in all_results.iter().enumerate() {
+            if i > 0 { print!(","); }
+            print!("{{\"card\":\"{}\",\"equity\":{:.6},\"trials\":{},\"group\":\"{}\"}}",
+                card_name(card), eq, trials, card_groups[card as usize].name());
+        }
+        print!("],\"groups\":[");
+        for (i, (g, members, eq)) in group_rows.iter().enumerate() {
+            if i > 0 { print!(","); }
+            let names: Vec<String> = members.iter().map(|&c| format!("\"{}\"", card_name(c))).collect();
+            print!("{{\"name\":\"{}\",\"count\":{},\"equity\":{:.6},\"cards\":[{}]}}",
+                g.name(), members.len(), eq, names.join(","));
+        }
+        print!("],\"excluded\":[");
+        for (i, name) in excluded_names.iter().enumerate() {
+            if i > 0 { print!(","); }
+            print!("\"{}\"", name);
+        }
+        println!("],\"totalTrials\":{},\"trialsPerCard\":{},\"numCandidates\":{},\"numClasses\":{},\"exact\":{},\"seed\":{},\"elapsedMs\":{},\"threadsUsed\":{},\"villainRange\":\"{}\"}}",
+            total_trials, trials_per_card, num_candidates, num_classes, exact_river, seed, elapsed_ms, num_threads(), villain_range_str);
+    } else {
+        eprintln!();
+        eprintln!("EQ Breakdown by next card ({}→{}):",
+            if is_turn_breakdown { "Flop" } else { "Turn" },
+            if is_turn_breakdown { "Turn" } else { "River" });
+        eprintln!("  {:>4}  {:<6}  {:>10}  {:>8}  {:<10}", "#", "Card", "Equity%", "Trials", "Group");
+        eprintln!("  ────  ──────  ──────────  ────────  ──────────");
+        for (i, &(card, eq, trials)) in all_results.iter().enumerate() {
+            eprintln!("  {:>4}  {:<6}  {:>9.3}%  {:>8}  {:<10}",
+                i + 1, card_name(card), eq * 100.0, trials, card_groups[card as usize].name());
+        }
+        eprintln!();
+        eprintln!("  {:<10}  {:>5}  {:>10}", "Group", "Cards", "Equity%");
+        eprintln!("  ──────────  ─────  ──────────");
+        for (g, members, eq) in &group_rows {
+            if members.is_empty() { continue; }
+            eprintln!("  {:<10}  {:>5}  {:>9.3}%", g.name(), members.len(), eq * 100.0);
         }
-        eprintln!("Could not parse hand: {}", hand_str);
+        eprintln!();
+        eprintln!("  Total trials: {}, Isomorphism classes: {}/{}, Time: {:.1}s, Seed: {}",
+            total_trials, num_classes, num_candidates, elapsed, seed);
+    }
+}
+
+/// Flop texture flags used to bucket the all-flops report.
+struct FlopTexture {
+    monotone: bool,
+    paired: bool,
+    connected: bool,
+}
+
+fn flop_texture(flop: &[u8; 3]) -> FlopTexture {
+    let mut ranks = [flop[0] % 13, flop[1] % 13, flop[2] % 13];
+    ranks.sort();
+    let monotone = flop[0] / 13 == flop[1] / 13 && flop[1] / 13 == flop[2] / 13;
+    let paired = ranks[0] == ranks[1] || ranks[1] == ranks[2];
+    // Three distinct ranks that fit in one five-rank straight window; the ace
+    // also plays low for the wheel.
+    let connected = !paired
+        && (ranks[2] - ranks[0] <= 4 || (ranks[2] == 12 && ranks[1] <= 3));
+    FlopTexture { monotone, paired, connected }
+}
+
+fn run_flops(args: &[String]) {
+    let hand_str = parse_flag(args, "--hand").unwrap_or_else(|| {
+        eprintln!("Usage: plo5_ranker flops --hand <hand> [--villain <hand> | --villain-range 100%|N%] [--dead <cards>] [--trials-per-flop N] [--seed S] [--json | --csv] [--rank-file path]");
+        eprintln!("Example: plo5_ranker flops --hand AcAdKhQh5s --villain KcKdJsTs9h --csv");
         std::process::exit(1);
     });
+    let trials_per_flop: u64 = parse_flag(args, "--trials-per-flop")
+        .and_then(|s| s.parse().ok()).unwrap_or(2000);
+    let seed: u64 = parse_flag(args, "--seed")
+        .and_then(|s| s.parse().ok()).unwrap_or(12345);
+    let json_output = args.iter().any(|a| a == "--json");
+    let csv_output = args.iter().any(|a| a == "--csv");
+    let dead_str = parse_flag(args, "--dead").unwrap_or_default();
+    let villain_str = parse_flag(args, "--villain");
+    let villain_range_str = parse_flag(args, "--villain-range").unwrap_or_else(|| "100%".into());
 
-    let board_cards = parse_cards_vec(&board_str);
-    let dead_cards = parse_cards_vec(&dead_str);
+    let hand = parse_hand(&hand_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse hand: {} ({})", hand_str, e)));
+    let villain_hand: Option<[u8; 5]> = villain_str.as_ref().map(|v| {
+        parse_hand(v).unwrap_or_else(|e| fail(json_output, format!("Could not parse villain hand: {} ({})", v, e)))
+    });
+    let villain_pct = parse_villain_range(&villain_range_str).unwrap_or_else(|| {
+        fail(json_output, format!("Invalid villain range: '{}'. Use N% (e.g. 10%, 20%, 100%)", villain_range_str))
+    });
+    let is_range_restricted = villain_hand.is_none() && villain_pct < 100.0;
+    let dead_cards = parse_cards(&dead_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse dead cards: {} ({})", dead_str, e)));
+
+    let mut excluded: Vec<u8> = hand.to_vec();
+    excluded.extend_from_slice(&dead_cards);
+    if let Some(v) = &villain_hand { excluded.extend_from_slice(v); }
+    let excluded_count = excluded.len();
+    excluded.sort();
+    excluded.dedup();
+    if excluded.len() != excluded_count {
+        fail(json_output, "Duplicate cards found among hand, villain, and dead cards".into());
+    }
+    let excluded_bm = card_bitmap(&excluded);
 
-    if board_cards.len() != 3 && board_cards.len() != 4 {
-        if json_output {
-            println!("{{\"ok\":false,\"error\":\"Breakdown requires board of 3 (flop) or 4 (turn) cards, got {}\"}}", board_cards.len());
-            std::process::exit(0);
+    let rank_pool: Vec<[u8; 5]> = if is_range_restricted {
+        let (rank_file, bin_path) = range_files(args).unwrap_or_else(|msg| fail(json_output, msg));
+        let rank_index = load_rank_index(&rank_file);
+        if !validate_rank_index(&rank_index, &bin_path) {
+            fail(json_output, "Rank index sanity check failed. Top equity must be >1.2x the fair share (60% heads-up), bottom <0.4x (20%). Regenerate with build_rank_index.".into());
+        }
+        let top_count = ((villain_pct / 100.0) * 2598960.0).floor() as usize;
+        let top_count = top_count.clamp(1, 2598960);
+        rank_index[..top_count].iter().map(|&idx| index_to_hand(idx)).collect()
+    } else {
+        Vec::new()
+    };
+
+    // Flops are isomorphic only if the swap also fixes villain and dead cards.
+    let villain_cards: Vec<u8> = villain_hand.map(|v| v.to_vec()).unwrap_or_default();
+    let flops = board_classes::<3>(&[&hand, &villain_cards, &dead_cards]);
+    let num_flops = flops.len();
+    let total_weight: u64 = flops.iter().map(|&(_, w)| w as u64).sum();
+    let exact = villain_hand.is_some();
+    let villain_label = match &villain_hand {
+        Some(v) => v.iter().map(|&c| card_name(c)).collect::<Vec<_>>().join(""),
+        None => villain_range_str.clone(),
+    };
+    let hand_label: String = hand.iter().map(|&c| card_name(c)).collect();
+
+    let t0 = Instant::now();
+    if !json_output {
+        eprintln!("╔══════════════════════════════════════════════╗");
+        eprintln!("║       PLO5 All-Flops Report                  ║");
+        eprintln!("╚══════════════════════════════════════════════╝");
+        eprintln!("  Hand:    {}", hand_label);
+        eprintln!("  Villain: {}", villain_label);
+        eprintln!("  Flops:   {} ({} isomorphism classes)", total_weight, num_flops);
+        if exact {
+            eprintln!("  Mode:    exact turn/river enumeration");
+        } else {
+            eprintln!("  Mode:    MC, {} trials per flop class", trials_per_flop);
+        }
+        eprintln!("  Seed:    {}", seed);
+        eprintln!();
+        eprintln!("[1/2] Initializing eval table...");
+    }
+    let table = init_eval_table();
+    if !json_output { eprintln!("[2/2] Computing equity on every flop..."); }
+
+    let hero_2s = two_card_subsets(&hand);
+    let villain_2s_fixed = villain_hand.map(|v| two_card_subsets(&v));
+    let num_threads = num_threads();
+    let chunk_size = num_flops.div_ceil(num_threads);
+
+    let results: Vec<Vec<(f64, u64)>> = parallel_map(num_threads, |t| {
+        let start = t * chunk_size;
+        let end = ((t + 1) * chunk_size).min(num_flops);
+        let mut thread_results: Vec<(f64, u64)> = Vec::with_capacity(end.saturating_sub(start));
+        for (flop, _) in &flops[start..end] {
+            let flop_bm = card_bitmap(flop);
+            let used_bm = excluded_bm | flop_bm;
+            let pool: Vec<u8> = (0..52u8).filter(|&c| used_bm & (1u64 << c) == 0).collect();
+            let mut wins = 0.0f64;
+            let mut count = 0u64;
+            let mut board = [0u8; 5];
+
+            if let Some(villain_2s) = &villain_2s_fixed {
+                for i in 0..pool.len() {
+                    for j in (i + 1)..pool.len() {
+                        board[..3].copy_from_slice(flop);
+                        board[3] = pool[i];
+                        board[4] = pool[j];
+                        board.sort();
+                        let board_3s = three_card_subsets(&board);
+                        let hero_rank = eval_best(&hero_2s, &board_3s, &table);
+                        let villain_rank = eval_best(villain_2s, &board_3s, &table);
+                        if hero_rank < villain_rank { wins += 1.0; }
+                        else if hero_rank == villain_rank { wins += 0.5; }
+                        count += 1;
+                    }
+                }
+            } else {
+                // Each flop gets its own stream so results do not depend
+                // on how flops are split across threads.
+                let mut rng = EngineRng::seeded(mix_seed(seed.wrapping_add(subset_index(flop) as u64)));
+                for _ in 0..trials_per_flop {
+                    let villain_arr = if is_range_restricted {
+                        let pool_len = rank_pool.len();
+                        let mut found = None;
+                        for _ in 0..200 {
+                            let cand = rank_pool[rng.gen_range(pool_len)];
+                            if card_bitmap(&cand) & used_bm == 0 {
+                                found = Some(cand);
+                                break;
+                            }
+                        }
+                        match found { Some(v) => v, None => continue }
+                    } else {
+                        sample_villain(&pool, &mut rng)
+                    };
+                    let villain_bm = card_bitmap(&villain_arr);
+                    let fill_pool: Vec<u8> = pool.iter().copied()
+                        .filter(|&c| villain_bm & (1u64 << c) == 0)
+                        .collect();
+                    let runout = sample_n(&fill_pool, 2, &mut rng);
+                    board[..3].copy_from_slice(flop);
+                    board[3] = runout[0];
+                    board[4] = runout[1];
+                    board.sort();
+                    let board_3s = three_card_subsets(&board);
+                    let hero_rank = eval_best(&hero_2s, &board_3s, &table);
+                    let villain_rank = eval_best(&two_card_subsets(&villain_arr), &board_3s, &table);
+                    if hero_rank < villain_rank { wins += 1.0; }
+                    else if hero_rank == villain_rank { wins += 0.5; }
+                    count += 1;
+                }
+            }
+            let eq = if count > 0 { wins / count as f64 } else { 0.5 };
+            thread_results.push((eq, count));
+        }
+        thread_results
+    });
+
+    struct FlopRow {
+        flop: [u8; 3],
+        weight: u32,
+        equity: f64,
+        showdowns: u64,
+    }
+    let mut rows: Vec<FlopRow> = flops.iter()
+        .zip(results.into_iter().flatten())
+        .map(|(&(flop, weight), (equity, showdowns))| FlopRow { flop, weight, equity, showdowns })
+        .collect();
+    rows.sort_by(|a, b| b.equity.partial_cmp(&a.equity).unwrap_or(std::cmp::Ordering::Equal));
+
+    let weighted = |pred: &dyn Fn(&FlopRow) -> bool| -> (u64, f64) {
+        let mut w_sum = 0u64;
+        let mut eq_sum = 0.0f64;
+        for row in rows.iter().filter(|r| pred(r)) {
+            w_sum += row.weight as u64;
+            eq_sum += row.weight as f64 * row.equity;
+        }
+        (w_sum, if w_sum > 0 { eq_sum / w_sum as f64 } else { 0.0 })
+    };
+    let (_, mean_equity) = weighted(&|_| true);
+    let (over70, _) = weighted(&|r| r.equity > 0.70);
+    let (under30, _) = weighted(&|r| r.equity < 0.30);
+    let over70_pct = over70 as f64 / total_weight as f64 * 100.0;
+    let under30_pct = under30 as f64 / total_weight as f64 * 100.0;
+    let textures: Vec<(&str, u64, f64)> = vec![
+        ("monotone", weighted(&|r| flop_texture(&r.flop).monotone)),
+        ("paired", weighted(&|r| flop_texture(&r.flop).paired)),
+        ("connected", weighted(&|r| flop_texture(&r.flop).connected)),
+    ].into_iter().map(|(name, (w, eq))| (name, w, eq)).collect();
+    let total_showdowns: u64 = rows.iter().map(|r| r.showdowns).sum();
+    let elapsed = t0.elapsed().as_secs_f64();
+    let elapsed_ms = (elapsed * 1000.0) as u64;
+    let flop_label = |flop: &[u8; 3]| -> String { flop.iter().map(|&c| card_name(c)).collect() };
+
+    if json_output {
+        print!("{{\"ok\":true,\"hand\":\"{}\",\"villain\":\"{}\",\"exact\":{},\"flopsTotal\":{},\"numClasses\":{},\"trialsPerFlop\":{},\"totalShowdowns\":{},",
+            hand_label, villain_label, exact, total_weight, num_flops, if exact { 0 } else { trials_per_flop }, total_showdowns);
+        print!("\"summary\":{{\"equity\":{:.6},\"over70Pct\":{:.4},\"under30Pct\":{:.4}}},\"textures\":[",
+            mean_equity, over70_pct, under30_pct);
+        for (i, &(name, w, eq)) in textures.iter().enumerate() {
+            if i > 0 { print!(","); }
+            print!("{{\"name\":\"{}\",\"flops\":{},\"freqPct\":{:.4},\"equity\":{:.6}}}",
+                name, w, w as f64 / total_weight as f64 * 100.0, eq);
+        }
+        print!("],\"flops\":[");
+        for (i, row) in rows.iter().enumerate() {
+            if i > 0 { print!(","); }
+            print!("{{\"board\":\"{}\",\"weight\":{},\"equity\":{:.6}}}", flop_label(&row.flop), row.weight, row.equity);
+        }
+        println!("],\"seed\":{},\"elapsedMs\":{}}}", seed, elapsed_ms);
+        return;
+    }
+
+    if csv_output {
+        println!("board,weight,equity,monotone,paired,connected");
+        for row in &rows {
+            let tx = flop_texture(&row.flop);
+            println!("{},{},{:.6},{},{},{}", flop_label(&row.flop), row.weight, row.equity,
+                tx.monotone as u8, tx.paired as u8, tx.connected as u8);
+        }
+    }
+
+    eprintln!();
+    eprintln!("  Equity over all flops: {:.3}%", mean_equity * 100.0);
+    eprintln!("  Hero > 70%:            {:.2}% of flops", over70_pct);
+    eprintln!("  Hero < 30%:            {:.2}% of flops", under30_pct);
+    eprintln!();
+    eprintln!("  {:<10}  {:>8}  {:>8}  {:>10}", "Texture", "Flops", "Freq%", "Equity%");
+    eprintln!("  ──────────  ────────  ────────  ──────────");
+    for &(name, w, eq) in &textures {
+        eprintln!("  {:<10}  {:>8}  {:>7.2}%  {:>9.3}%", name, w, w as f64 / total_weight as f64 * 100.0, eq * 100.0);
+    }
+    if !csv_output {
+        let show = rows.len().min(10);
+        eprintln!();
+        eprintln!("  Best flops:");
+        for row in &rows[..show] {
+            eprintln!("    {}  {:>9.3}%  (x{})", flop_label(&row.flop), row.equity * 100.0, row.weight);
+        }
+        eprintln!("  Worst flops:");
+        for row in &rows[rows.len() - show..] {
+            eprintln!("    {}  {:>9.3}%  (x{})", flop_label(&row.flop), row.equity * 100.0, row.weight);
+        }
+    }
+    eprintln!();
+    eprintln!("  Showdowns: {}, Time: {:.1}s, Seed: {}", total_showdowns, elapsed, seed);
+}
+
+/// Rank masks of the ten straight windows, wheel first.
+const STRAIGHT_WINDOWS: [u16; 10] = [
+    0b1_0000_0000_1111,
+    0b11111,
+    0b11111 << 1,
+    0b11111 << 2,
+    0b11111 << 3,
+    0b11111 << 4,
+    0b11111 << 5,
+    0b11111 << 6,
+    0b11111 << 7,
+    0b11111 << 8,
+];
+
+fn rank_mask(cards: &[u8]) -> u16 {
+    cards.iter().fold(0u16, |m, &c| m | (1 << (c % 13)))
+}
+
+/// Whether two hole ranks from `hole_mask` plus three board ranks from
+/// `board_mask` complete a straight window, ignoring suits.
+fn makes_straight(hole_mask: u16, board_mask: u16) -> bool {
+    STRAIGHT_WINDOWS.iter().any(|&window| {
+        let held = hole_mask & window;
+        let mut pairs = held;
+        while pairs != 0 {
+            let a = pairs & pairs.wrapping_neg();
+            pairs &= pairs - 1;
+            let mut rest = pairs;
+            while rest != 0 {
+                let b = rest & rest.wrapping_neg();
+                rest &= rest - 1;
+                if window & !(a | b) & !board_mask == 0 { return true; }
+            }
+        }
+        false
+    })
+}
+
+/// Whether the hand makes a flush with two suited hole cards and three
+/// board cards of the same suit.
+fn makes_flush(hand: &[u8; 5], board: &[u8]) -> bool {
+    (0..4u8).any(|suit| {
+        hand.iter().filter(|&&c| c / 13 == suit).count() >= 2
+            && board.iter().filter(|&&c| c / 13 == suit).count() >= 3
+    })
+}
+
+/// What the hero holds on a board: the best made hand with the exact two
+/// hole cards and three board cards it uses, and the draws to the next card.
+struct Classification {
+    category: u8,
+    hole_used: [u8; 2],
+    board_used: [u8; 3],
+    flush_outs: Vec<u8>,
+    nut_flush_draw: bool,
+    straight_outs: Vec<u8>,
+    backdoor_flush: bool,
+    backdoor_straight: bool,
+    set_outs: Vec<u8>,
+}
+
+impl Classification {
+    /// Gutshot, open-ender or wrap, by how many distinct ranks complete the straight.
+    fn straight_draw(&self) -> Option<&'static str> {
+        match rank_mask(&self.straight_outs).count_ones() {
+            0 => None,
+            1 => Some("gutshot"),
+            2 => Some("open_ended"),
+            _ => Some("wrap"),
+        }
+    }
+
+    /// Distinct cards that complete a flush, a straight or a set.
+    fn outs(&self) -> Vec<u8> {
+        let mut outs: Vec<u8> = self.flush_outs.iter()
+            .chain(&self.straight_outs)
+            .chain(&self.set_outs)
+            .copied()
+            .collect();
+        outs.sort();
+        outs.dedup();
+        outs
+    }
+}
+
+/// Classifies `hand` on a 3-5 card board under the exactly-two-hole-cards
+/// rule. Outs are counted among cards not in hand, board or `dead`.
+fn classify_hand(hand: &[u8; 5], board: &[u8], dead: &[u8]) -> Classification {
+    let mut best = (u64::MAX, [0u8; 2], [0u8; 3]);
+    let mut five = [0u8; 5];
+    for h in &two_card_subsets(hand) {
+        for i in 0..board.len() {
+            for j in (i + 1)..board.len() {
+                for k in (j + 1)..board.len() {
+                    let b = [board[i], board[j], board[k]];
+                    five[..2].copy_from_slice(h);
+                    five[2..].copy_from_slice(&b);
+                    let key = hand_sort_key(&five);
+                    if key < best.0 { best = (key, *h, b); }
+                }
+            }
+        }
+    }
+
+    let mut result = Classification {
+        category: (best.0 / 14u64.pow(5)) as u8,
+        hole_used: best.1,
+        board_used: best.2,
+        flush_outs: Vec::new(),
+        nut_flush_draw: false,
+        straight_outs: Vec::new(),
+        backdoor_flush: false,
+        backdoor_straight: false,
+        set_outs: Vec::new(),
+    };
+    if board.len() >= 5 { return result; }
+
+    let used_bm = card_bitmap(hand) | card_bitmap(board) | card_bitmap(dead);
+    let unseen: Vec<u8> = (0..52u8).filter(|&c| used_bm & (1u64 << c) == 0).collect();
+    let hole_mask = rank_mask(hand);
+    let board_mask = rank_mask(board);
+    let has_flush = makes_flush(hand, board);
+    let has_straight = makes_straight(hole_mask, board_mask);
+
+    let mut next_board = board.to_vec();
+    next_board.push(0);
+    for &card in &unseen {
+        *next_board.last_mut().unwrap() = card;
+        if !has_flush && makes_flush(hand, &next_board) {
+            result.flush_outs.push(card);
         }
-        eprintln!("Breakdown requires board of 3 (flop) or 4 (turn) cards, got {}", board_cards.len());
+        if !has_straight && makes_straight(hole_mask, board_mask | (1 << (card % 13))) {
+            result.straight_outs.push(card);
+        }
+    }
+
+    for suit in 0..4u8 {
+        let held: Vec<u8> = hand.iter().filter(|&&c| c / 13 == suit).map(|&c| c % 13).collect();
+        let on_board = board.iter().filter(|&&c| c / 13 == suit).count();
+        if held.len() < 2 || has_flush { continue; }
+        if on_board == 2 {
+            // The nut draw holds the highest rank of the suit not on the board.
+            let top = (0..13u8).rev().find(|&r| board.iter().all(|&c| c != suit * 13 + r));
+            if top.is_some_and(|r| held.contains(&r)) { result.nut_flush_draw = true; }
+        }
+        if on_board == 1 && board.len() == 3 { result.backdoor_flush = true; }
+    }
+
+    if board.len() == 3 && !has_straight && result.straight_outs.is_empty() {
+        result.backdoor_straight = (0..13).any(|a| (a..13).any(|b| {
+            makes_straight(hole_mask, board_mask | (1 << a) | (1 << b))
+        }));
+    }
+
+    for rank in 0..13u8 {
+        let held = hand.iter().filter(|&&c| c % 13 == rank).count();
+        if held >= 2 && board_mask & (1 << rank) == 0 {
+            result.set_outs.extend(unseen.iter().filter(|&&c| c % 13 == rank));
+        }
+    }
+    result
+}
+
+fn run_classify(args: &[String]) {
+    let hand_str = parse_flag(args, "--hand").unwrap_or_default();
+    let board_str = parse_flag(args, "--board").unwrap_or_default();
+    if hand_str.is_empty() || board_str.is_empty() {
+        eprintln!("Usage: plo5_ranker classify --hand <hand> --board <3-5 cards> [--dead <cards>] [--json]");
+        eprintln!("Example: plo5_ranker classify --hand AhKhQd9c8c --board Jh7c2h");
+        std::process::exit(1);
+    }
+    let json_output = args.iter().any(|a| a == "--json");
+    let dead_str = parse_flag(args, "--dead").unwrap_or_default();
+
+    let hand = parse_hand(&hand_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse hand: {} ({})", hand_str, e)));
+    let board = parse_cards(&board_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse board: {} ({})", board_str, e)));
+    if board.len() < 3 || board.len() > 5 {
+        fail(json_output, format!("Board must have 3 to 5 cards, got {}", board.len()));
+    }
+    let dead = parse_cards(&dead_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse dead cards: {} ({})", dead_str, e)));
+    let mut all: Vec<u8> = hand.iter().chain(&board).chain(&dead).copied().collect();
+    let all_count = all.len();
+    all.sort();
+    all.dedup();
+    if all.len() != all_count {
+        fail(json_output, "Duplicate cards found among hand, board, and dead cards".into());
+    }
+
+    let c = classify_hand(&hand, &board, &dead);
+    let names = |cards: &[u8]| -> Vec<String> { cards.iter().map(|&x| card_name(x)).collect() };
+    let quoted = |cards: &[u8]| -> String {
+        cards.iter().map(|&x| format!("\"{}\"", card_name(x))).collect::<Vec<_>>().join(",")
+    };
+    let outs = c.outs();
+
+    if json_output {
+        let straight_draw = match c.straight_draw() {
+            Some(kind) => format!("\"{}\"", kind),
+            None => "null".into(),
+        };
+        println!("{{\"ok\":true,\"hand\":\"{}\",\"board\":\"{}\",\"category\":\"{}\",\"categoryIndex\":{},\"holeUsed\":[{}],\"boardUsed\":[{}],\"draws\":{{\"flushDraw\":{},\"nutFlushDraw\":{},\"flushOuts\":[{}],\"straightDraw\":{},\"straightOuts\":[{}],\"backdoorFlush\":{},\"backdoorStraight\":{},\"setOuts\":[{}]}},\"outs\":{}}}",
+            names(&hand).join(""), names(&board).join(""),
+            CATEGORY_NAMES[c.category as usize], c.category,
+            quoted(&c.hole_used), quoted(&c.board_used),
+            !c.flush_outs.is_empty(), c.nut_flush_draw, quoted(&c.flush_outs),
+            straight_draw, quoted(&c.straight_outs),
+            c.backdoor_flush, c.backdoor_straight, quoted(&c.set_outs), outs.len());
+        return;
+    }
+
+    eprintln!("  Hand:   {}", names(&hand).join(""));
+    eprintln!("  Board:  {}", names(&board).join(""));
+    eprintln!();
+    eprintln!("  Made hand:  {} ({} + {})", CATEGORY_NAMES[c.category as usize],
+        names(&c.hole_used).join(""), names(&c.board_used).join(""));
+    if board.len() == 5 { return; }
+    if !c.flush_outs.is_empty() {
+        eprintln!("  {}:  {} outs ({})",
+            if c.nut_flush_draw { "Nut flush draw" } else { "Flush draw" },
+            c.flush_outs.len(), names(&c.flush_outs).join(" "));
+    }
+    if let Some(kind) = c.straight_draw() {
+        let label = match kind { "gutshot" => "Gutshot", "open_ended" => "Open-ended", _ => "Wrap" };
+        eprintln!("  {}:  {} outs ({})", label, c.straight_outs.len(), names(&c.straight_outs).join(" "));
+    }
+    if !c.set_outs.is_empty() {
+        eprintln!("  Set outs:  {} ({})", c.set_outs.len(), names(&c.set_outs).join(" "));
+    }
+    if c.backdoor_flush { eprintln!("  Backdoor flush draw"); }
+    if c.backdoor_straight { eprintln!("  Backdoor straight draw"); }
+    eprintln!("  Total outs: {}", outs.len());
+}
+
+/// Hero's standing among every 2-card holding the unseen deck can make on
+/// a board: `position` is 1 for the nuts, plus one for each distinct hand
+/// value that beats hero; `better` lists the beating pairs, best first.
+struct NutStatus {
+    hero_rank: u16,
+    position: usize,
+    total_combos: usize,
+    better: Vec<([u8; 2], u16)>,
+}
+
+/// Best rank a 2-card holding makes with three cards of the board.
+fn pair_rank(pair: &[u8; 2], board_3s: &[[u8; 3]; 10], table: &[u16]) -> u16 {
+    let mut best = u16::MAX;
+    let mut merged = [0u8; 5];
+    for b in board_3s {
+        merge5(pair, b, &mut merged);
+        best = best.min(table[comb_index(&merged)]);
+    }
+    best
+}
+
+fn nut_status(hand: &[u8; 5], board: &[u8], unseen: &[u8], table: &[u16]) -> NutStatus {
+    let mut sorted_board = board.to_vec();
+    sorted_board.sort();
+    let board_3s = three_card_subsets_from_slice(&sorted_board);
+    let hero_rank = eval_best(&two_card_subsets(hand), &board_3s, table);
+    let mut better: Vec<([u8; 2], u16)> = Vec::new();
+    let mut total_combos = 0;
+    for i in 0..unseen.len() {
+        for j in (i + 1)..unseen.len() {
+            let pair = [unseen[i], unseen[j]];
+            let rank = pair_rank(&pair, &board_3s, table);
+            if rank < hero_rank { better.push((pair, rank)); }
+            total_combos += 1;
+        }
+    }
+    better.sort_by_key(|&(_, rank)| rank);
+    let mut distinct: Vec<u16> = better.iter().map(|&(_, rank)| rank).collect();
+    distinct.dedup();
+    NutStatus { hero_rank, position: distinct.len() + 1, total_combos, better }
+}
+
+/// Fraction of river runouts on which hero holds the nuts, i.e. no 2-card
+/// holding from the remaining deck beats hero (ties still count as nuts).
+fn nut_probability(hand: &[u8; 5], board: &[u8], unseen: &[u8], table: &[u16]) -> (f64, usize) {
+    let hero_2s = two_card_subsets(hand);
+    let runouts: Vec<Vec<u8>> = match board.len() {
+        3 => (0..unseen.len())
+            .flat_map(|i| ((i + 1)..unseen.len()).map(move |j| vec![unseen[i], unseen[j]]))
+            .collect(),
+        4 => unseen.iter().map(|&c| vec![c]).collect(),
+        _ => return (0.0, 0),
+    };
+    let num_runouts = runouts.len();
+    let num_threads = num_threads();
+    let chunk_size = num_runouts.div_ceil(num_threads);
+
+    let nut_count: usize = parallel_map(num_threads, |t| {
+        let start = (t * chunk_size).min(num_runouts);
+        let end = ((t + 1) * chunk_size).min(num_runouts);
+        let mut nuts = 0usize;
+        for runout in &runouts[start..end] {
+            let mut full_board = board.to_vec();
+            full_board.extend_from_slice(runout);
+            full_board.sort();
+            let board_3s = three_card_subsets_from_slice(&full_board);
+            let hero_rank = eval_best(&hero_2s, &board_3s, table);
+            let pool: Vec<u8> = unseen.iter().copied().filter(|c| !runout.contains(c)).collect();
+            let beaten = (0..pool.len()).any(|i| ((i + 1)..pool.len()).any(|j| {
+                pair_rank(&[pool[i], pool[j]], &board_3s, table) < hero_rank
+            }));
+            if !beaten { nuts += 1; }
+        }
+        nuts
+    }).into_iter().sum();
+    (nut_count as f64 / num_runouts as f64, num_runouts)
+}
+
+fn run_nuts(args: &[String]) {
+    let hand_str = parse_flag(args, "--hand").unwrap_or_default();
+    let board_str = parse_flag(args, "--board").unwrap_or_default();
+    if hand_str.is_empty() || board_str.is_empty() {
+        eprintln!("Usage: plo5_ranker nuts --hand <hand> --board <3-5 cards> [--dead <cards>] [--examples N] [--json]");
+        eprintln!("Example: plo5_ranker nuts --hand AhKhQd9c8c --board Jh7c2hTd");
+        std::process::exit(1);
+    }
+    let json_output = args.iter().any(|a| a == "--json");
+    let dead_str = parse_flag(args, "--dead").unwrap_or_default();
+    let num_examples: usize = parse_flag(args, "--examples")
+        .and_then(|s| s.parse().ok()).unwrap_or(10);
+
+    let hand = parse_hand(&hand_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse hand: {} ({})", hand_str, e)));
+    let mut board = parse_cards(&board_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse board: {} ({})", board_str, e)));
+    if board.len() < 3 || board.len() > 5 {
+        fail(json_output, format!("Board must have 3 to 5 cards, got {}", board.len()));
+    }
+    board.sort();
+    let dead = parse_cards(&dead_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse dead cards: {} ({})", dead_str, e)));
+    let mut all: Vec<u8> = hand.iter().chain(&board).chain(&dead).copied().collect();
+    let all_count = all.len();
+    all.sort();
+    all.dedup();
+    if all.len() != all_count {
+        fail(json_output, "Duplicate cards found among hand, board, and dead cards".into());
+    }
+    let used_bm = card_bitmap(&all);
+    let unseen: Vec<u8> = (0..52u8).filter(|&c| used_bm & (1u64 << c) == 0).collect();
+
+    let t0 = Instant::now();
+    let table = init_eval_table();
+    let status = nut_status(&hand, &board, &unseen, &table);
+    let (nut_prob, num_runouts) = nut_probability(&hand, &board, &unseen, &table);
+    let elapsed_ms = (t0.elapsed().as_secs_f64() * 1000.0) as u64;
+
+    let category = best_category(&hand, &board);
+    let pair_category = |pair: &[u8; 2]| {
+        let mut five = [0u8; 5];
+        let mut best = u64::MAX;
+        for b in &three_card_subsets_from_slice(&board) {
+            merge5(pair, b, &mut five);
+            best = best.min(hand_sort_key(&five));
+        }
+        CATEGORY_NAMES[(best / 14u64.pow(5)) as usize]
+    };
+    let examples = &status.better[..status.better.len().min(num_examples)];
+    let hand_label: String = hand.iter().map(|&c| card_name(c)).collect();
+    let board_label: String = board.iter().map(|&c| card_name(c)).collect();
+
+    if json_output {
+        let beaters: Vec<String> = examples.iter().map(|(pair, _)| {
+            format!("{{\"cards\":\"{}{}\",\"category\":\"{}\"}}",
+                card_name(pair[0]), card_name(pair[1]), pair_category(pair))
+        }).collect();
+        let nut_prob_json = if num_runouts > 0 { format!("{:.6}", nut_prob) } else { "null".into() };
+        println!("{{\"ok\":true,\"hand\":\"{}\",\"board\":\"{}\",\"category\":\"{}\",\"handRank\":{},\"position\":{},\"isNuts\":{},\"betterCombos\":{},\"totalCombos\":{},\"beaters\":[{}],\"nutProbability\":{},\"runouts\":{},\"elapsedMs\":{}}}",
+            hand_label, board_label, CATEGORY_NAMES[category as usize], status.hero_rank,
+            status.position, status.better.is_empty(), status.better.len(), status.total_combos,
+            beaters.join(","), nut_prob_json, num_runouts, elapsed_ms);
+        return;
+    }
+
+    eprintln!("  Hand:   {}", hand_label);
+    eprintln!("  Board:  {}", board_label);
+    eprintln!();
+    eprintln!("  Made hand:     {}", CATEGORY_NAMES[category as usize]);
+    if status.better.is_empty() {
+        eprintln!("  Nut status:    THE NUTS ({} combos checked)", status.total_combos);
+    } else {
+        eprintln!("  Nut status:    #{} ({} of {} combos beat hero)",
+            status.position, status.better.len(), status.total_combos);
+        eprintln!();
+        eprintln!("  {:<8}  {:<16}", "Beater", "Makes");
+        eprintln!("  ────────  ────────────────");
+        for (pair, _) in examples {
+            eprintln!("  {:<8}  {:<16}", format!("{}{}", card_name(pair[0]), card_name(pair[1])), pair_category(pair));
+        }
+    }
+    if num_runouts > 0 {
+        eprintln!();
+        eprintln!("  Nuts by the river: {:.2}% of {} runouts", nut_prob * 100.0, num_runouts);
+    }
+}
+
+fn run_showdown(args: &[String]) {
+    let board_str = parse_flag(args, "--board").unwrap_or_default();
+    let hand_str = parse_flag(args, "--hand").unwrap_or_default();
+    let villain_str = parse_flag(args, "--villain").unwrap_or_default();
+    if board_str.is_empty() || hand_str.is_empty() || villain_str.is_empty() {
+        eprintln!("Usage: plo5_ranker showdown --board <5 cards> --hand <hand> --villain <hand>[:<hand>...] [--json]");
+        eprintln!("Example: plo5_ranker showdown --board Kh7d7c2sKs --hand AcAdKc9h8h --villain 7h6h5d4c3c:QsJsTh9d8d");
         std::process::exit(1);
     }
+    let json_output = args.iter().any(|a| a == "--json");
+
+    let mut board = parse_cards(&board_str)
+        .unwrap_or_else(|e| fail(json_output, format!("Could not parse board: {} ({})", board_str, e)));
+    if board.len() != 5 {
+        fail(json_output, format!("Showdown needs a 5-card board, got {}", board.len()));
+    }
+    board.sort();
+    let board: [u8; 5] = board.try_into().unwrap();
+    let mut hands: Vec<[u8; 5]> = Vec::new();
+    for s in std::iter::once(hand_str.as_str()).chain(split_hand_list(&villain_str)) {
+        hands.push(parse_hand(s)
+            .unwrap_or_else(|e| fail(json_output, format!("Could not parse hand: {} ({})", s, e))));
+    }
+    let mut all: Vec<u8> = hands.iter().flatten().chain(&board).copied().collect();
+    let all_count = all.len();
+    all.sort();
+    all.dedup();
+    if all.len() != all_count {
+        fail(json_output, "Duplicate cards found among hands and board".into());
+    }
+
+    let table = init_eval_table();
+    let results: Vec<(u16, [u8; 2], [u8; 3])> = hands.iter().map(|h| best_hand(h, &board, &table)).collect();
+    let best_rank = results.iter().map(|r| r.0).min().unwrap();
+    let winners: Vec<usize> = (0..hands.len()).filter(|&i| results[i].0 == best_rank).collect();
+    let names = |cards: &[u8]| -> String { cards.iter().map(|&c| card_name(c)).collect() };
+    let player = |i: usize| if i == 0 { "Hero".to_string() } else { format!("Villain {}", i) };
+    let description = |r: &(u16, [u8; 2], [u8; 3])| {
+        let mut five = [0u8; 5];
+        merge5(&r.1, &r.2, &mut five);
+        describe_hand(&five)
+    };
+
+    if json_output {
+        let players: Vec<String> = hands.iter().zip(&results).enumerate().map(|(i, (h, r))| {
+            let quoted = |cards: &[u8]| cards.iter().map(|&c| format!("\"{}\"", card_name(c))).collect::<Vec<_>>().join(",");
+            format!("{{\"name\":\"{}\",\"hand\":\"{}\",\"holeUsed\":[{}],\"boardUsed\":[{}],\"category\":\"{}\",\"description\":\"{}\",\"rank\":{},\"winner\":{}}}",
+                player(i), names(h), quoted(&r.1), quoted(&r.2), CATEGORY_NAMES[rank_to_category(r.0) as usize],
+                description(r), r.0, winners.contains(&i))
+        }).collect();
+        let winner_ids: Vec<String> = winners.iter().map(|i| i.to_string()).collect();
+        println!("{{\"ok\":true,\"board\":\"{}\",\"players\":[{}],\"winners\":[{}],\"split\":{}}}",
+            names(&board), players.join(","), winner_ids.join(","), winners.len() > 1);
+        return;
+    }
+
+    eprintln!();
+    eprintln!("  Board: {}", names(&board));
+    eprintln!();
+    eprintln!("  {:<10}  {:<10}  {:<5}  {:<7}  {:<28}", "Player", "Hand", "Hole", "Board", "Best hand");
+    eprintln!("  ──────────  ──────────  ─────  ───────  ────────────────────────────");
+    for (i, (h, r)) in hands.iter().zip(&results).enumerate() {
+        eprintln!("  {:<10}  {:<10}  {:<5}  {:<7}  {:<28}{}", player(i), names(h), names(&r.1), names(&r.2),
+            description(r), if winners.contains(&i) { "  ◀" } else { "" });
+    }
+    eprintln!();
+    if winners.len() > 1 {
+        let split: Vec<String> = winners.iter().map(|&i| player(i)).collect();
+        eprintln!("  Split pot: {}", split.join(", "));
+    } else {
+        eprintln!("  {} wins with {}", player(winners[0]), description(&results[winners[0]]));
+    }
+}
+
+fn run_bench_eval(args: &[String]) {
+    let showdowns: usize = parse_flag(args, "--showdowns")
+        .and_then(|s| s.parse().ok()).unwrap_or(1_000_000);
+    let seed: u64 = parse_flag(args, "--seed")
+        .and_then(|s| s.parse().ok()).unwrap_or(12345);
+    let json_output = args.iter().any(|a| a == "--json");
+
+    if !json_output {
+        eprintln!("╔══════════════════════════════════════════════╗");
+        eprintln!("║       PLO5 Evaluator Benchmark               ║");
+        eprintln!("╚══════════════════════════════════════════════╝");
+        eprintln!("  Showdowns: {}", showdowns);
+        eprintln!("  Seed:      {}", seed);
+        eprintln!();
+    }
+
+    let t0 = Instant::now();
+    let table = init_eval_table();
+    let table_secs = t0.elapsed().as_secs_f64();
+    let t1 = Instant::now();
+    let rank_mask = RankMaskEvaluator::new(&table);
+    let rank_mask_secs = t1.elapsed().as_secs_f64();
+
+    let deck: Vec<u8> = (0..52).collect();
+    let mut rng = EngineRng::seeded(mix_seed(seed));
+    let deals: Vec<([u8; 5], [u8; 5])> = (0..showdowns).map(|_| {
+        let cards = sample_n(&deck, 10, &mut rng);
+        let mut hand: [u8; 5] = cards[..5].try_into().unwrap();
+        let mut board: [u8; 5] = cards[5..].try_into().unwrap();
+        hand.sort();
+        board.sort();
+        (hand, board)
+    }).collect();
+
+    let lookup = LookupEvaluator { table: &table };
+    let evaluators: [(&dyn OmahaEvaluator, f64); 2] = [(&lookup, table_secs), (&rank_mask, rank_mask_secs)];
+    let mut reference: Vec<u16> = Vec::new();
+    let mut rows: Vec<(&'static str, f64, f64, usize)> = Vec::new();
+    for (eval, setup_secs) in evaluators {
+        let t = Instant::now();
+        let ranks: Vec<u16> = deals.iter().map(|(h, b)| eval.best_rank(h, b)).collect();
+        let secs = t.elapsed().as_secs_f64();
+        if reference.is_empty() { reference = ranks.clone(); }
+        let mismatches = ranks.iter().zip(&reference).filter(|(a, b)| a != b).count();
+        rows.push((eval.name(), setup_secs, showdowns as f64 / secs, mismatches));
+    }
+    let baseline_rate = rows[0].2;
+
+    if json_output {
+        let items: Vec<String> = rows.iter().map(|&(name, setup, rate, mismatches)| {
+            format!("{{\"name\":\"{}\",\"setupMs\":{},\"showdownsPerSec\":{:.0},\"speedup\":{:.3},\"mismatches\":{}}}",
+                name, (setup * 1000.0) as u64, rate, rate / baseline_rate, mismatches)
+        }).collect();
+        println!("{{\"ok\":true,\"showdowns\":{},\"seed\":{},\"evaluators\":[{}]}}",
+            showdowns, seed, items.join(","));
+        return;
+    }
 
-    let mut excluded: Vec<u8> = Vec::new();
-    for &c in hand.iter() { excluded.push(c); }
-    for &c in &board_cards { excluded.push(c); }
-    for &c in &dead_cards { excluded.push(c); }
-    excluded.sort();
-    excluded.dedup();
-    if excluded.len() != hand.len() + board_cards.len() + dead_cards.len() {
-        if json_output {
-            println!("{{\"ok\":false,\"error\":\"Duplicate cards found among hand, board, and dead cards\"}}");
-            std::process::exit(0);
-        }
-        eprintln!("Duplicate cards found");
-        std::process::exit(1);
+    eprintln!();
+    eprintln!("  {:<10}  {:>9}  {:>14}  {:>8}  {:>10}", "Evaluator", "Setup", "Showdowns/s", "Speedup", "Mismatches");
+    eprintln!("  ──────────  ─────────  ──────────────  ────────  ──────────");
+    for &(name, setup, rate, mismatches) in &rows {
+        eprintln!("  {:<10}  {:>8.2}s  {:>14.0}  {:>7.2}x  {:>10}", name, setup, rate, rate / baseline_rate, mismatches);
+    }
+    if rows.iter().any(|r| r.3 > 0) {
+        eprintln!();
+        eprintln!("  WARNING: evaluators disagree on some showdowns");
     }
+}
 
-    let excluded_bm = card_bitmap(&excluded);
-    let candidate_cards: Vec<u8> = (0..52u8)
-        .filter(|c| excluded_bm & (1u64 << (*c as u64)) == 0)
-        .collect();
-    let num_candidates = candidate_cards.len();
-    let is_turn_breakdown = board_cards.len() == 3;
+/// Monte Carlo equity for `bench` through `equity_trials`, set up as
+/// `run_equity` does without --iso. Returns the number of showdowns played.
+fn bench_equity(hand: &[u8; 5], board: &[u8], rank_pool: &[[u8; 5]], trials: u64, seed: u64,
+                evaluator: &dyn OmahaEvaluator) -> u64 {
+    let excluded_bm = card_bitmap(hand) | card_bitmap(board);
+    let remaining: Vec<u8> = (0..52u8).filter(|&c| excluded_bm & (1u64 << c) == 0).collect();
+    let strata = [Stratum { cards: Vec::new(), end: trials as usize, weight: 1.0 }];
+    equity_trials(&EquitySetup {
+        hand: *hand,
+        board_cards: board,
+        excluded_bm,
+        remaining: &remaining,
+        strata: &strata,
+        rank_pool,
+        board_to_fill: 5 - board.len(),
+        seed,
+        evaluator,
+        threads: num_threads(),
+        legacy_rng: LEGACY_RNG.load(Ordering::Relaxed),
+    }).total
+}
 
-    let trials_per_card = (trials_budget / num_candidates as u64).max(100);
+/// Exact river breakdown for `bench` through `breakdown_card`, one call per
+/// river isomorphism class as in `run_breakdown`. Returns the number of
+/// showdowns played.
+fn bench_breakdown(hand: &[u8; 5], turn: &[u8], table: &[u16]) -> u64 {
+    let setup = BreakdownSetup {
+        hand: *hand,
+        board_cards: turn,
+        excluded_bm: card_bitmap(hand) | card_bitmap(turn),
+        scenarios: &[],
+        rank_pool: &[],
+        exact_river: true,
+        table,
+    };
+    let rivers = board_classes::<1>(&[hand, turn]);
+    parallel_map(rivers.len(), |i| breakdown_card(&setup, rivers[i].0[0]).1).iter().sum()
+}
 
-    let rank_pool: Vec<[u8; 5]> = if is_range_restricted {
-        let rank_index = load_rank_index(&rank_file);
-        let top_count = ((villain_pct / 100.0) * 2598960.0).floor() as usize;
-        let top_count = top_count.max(1).min(2598960);
-        rank_index[..top_count].iter().map(|&idx| index_to_hand(idx)).collect()
-    } else {
-        Vec::new()
+fn run_bench(args: &[String]) {
+    let seed: u64 = parse_flag(args, "--seed")
+        .and_then(|s| s.parse().ok()).unwrap_or(12345);
+    let trials: u64 = parse_flag(args, "--trials")
+        .and_then(|s| s.parse().ok()).unwrap_or(200_000);
+    let out_path = parse_flag(args, "--out").unwrap_or_else(|| "bench.json".into());
+    let rank_file = parse_flag(args, "--rank-file").unwrap_or_else(|| "public/rank_index_all_2598960.u32".into());
+    let json_output = args.iter().any(|a| a == "--json");
+    let max_threads = num_cpus();
+    let thread_counts: Vec<usize> = match parse_flag(args, "--threads-list") {
+        Some(list) => list.split(',').filter_map(|s| s.trim().parse().ok()).filter(|&n| n > 0).collect(),
+        None => {
+            let mut counts = vec![1, max_threads];
+            counts.dedup();
+            counts
+        }
     };
 
-    let t0 = Instant::now();
-    if !json_output { eprintln!("[1/2] Initializing eval table..."); }
-    let table = init_eval_table();
     if !json_output {
-        eprintln!("       Done in {:.2}s", t0.elapsed().as_secs_f64());
-        eprintln!("[2/2] Computing breakdown for {} candidate cards ({} trials each)...",
-            num_candidates, trials_per_card);
-        eprintln!("       Board: {} cards, next street: {}",
-            board_cards.len(), if is_turn_breakdown { "turn" } else { "river" });
+        eprintln!("╔══════════════════════════════════════════════╗");
+        eprintln!("║       PLO5 Engine Benchmark                  ║");
+        eprintln!("╚══════════════════════════════════════════════╝");
+        eprintln!("  Version: {}", env!("CARGO_PKG_VERSION"));
+        eprintln!("  Seed:    {}", seed);
+        eprintln!("  Trials:  {} per equity case", trials);
+        eprintln!("  Threads: {:?} (detected {})", thread_counts, max_threads);
+        eprintln!();
     }
 
-    let hero_2s = two_card_subsets(&hand);
-    let hero_bm = card_bitmap(&hand);
-    let board_bm = card_bitmap(&board_cards);
-    let num_threads = num_cpus();
-
-    let mut crn_scenarios: Vec<(Vec<u8>, Vec<u64>)> = Vec::new();
-    if is_turn_breakdown {
-        let mut scenario_rng = Xorshift64::new(mix_seed(seed.wrapping_add(999)));
-        for _ in 0..trials_per_card {
-            let river_card_idx = scenario_rng.gen_range(52);
-            let river_card = river_card_idx as u8;
-            let villain_seed = scenario_rng.next();
-            crn_scenarios.push((vec![river_card], vec![villain_seed]));
-        }
+    let t0 = Instant::now();
+    let table = init_eval_table();
+    let table_ms = t0.elapsed().as_secs_f64() * 1000.0;
+    let evaluator = selected_evaluator(&table);
+
+    // Raw evaluator throughput, single-threaded, on one fixed set of deals.
+    let deck: Vec<u8> = (0..52).collect();
+    let mut deal_rng = EngineRng::seeded(mix_seed(seed));
+    let deals: Vec<([u8; 5], [u8; 5])> = (0..trials).map(|_| {
+        let cards = sample_n(&deck, 10, &mut deal_rng);
+        let mut hand: [u8; 5] = cards[..5].try_into().unwrap();
+        let mut board: [u8; 5] = cards[5..].try_into().unwrap();
+        hand.sort();
+        board.sort();
+        (hand, board)
+    }).collect();
+    let t = Instant::now();
+    let checksum: u64 = deals.iter()
+        .map(|(h, b)| eval_best(&two_card_subsets(h), &three_card_subsets(b), &table) as u64)
+        .sum();
+    let eval_rate = trials as f64 / t.elapsed().as_secs_f64();
+    std::hint::black_box(checksum);
+
+    let hand = parse_hand("AcAdKhQh5s").unwrap();
+    let flop = parse_cards_vec("Jh8h3c");
+    let turn = parse_cards_vec("Jh8h3c2d");
+    let rank_index = if std::path::Path::new(&rank_file).exists() {
+        Some(load_rank_index(&rank_file))
     } else {
-        let mut scenario_rng = Xorshift64::new(mix_seed(seed.wrapping_add(999)));
-        for _ in 0..trials_per_card {
-            let villain_seed = scenario_rng.next();
-            crn_scenarios.push((vec![], vec![villain_seed]));
+        None
+    };
+    let range_pool = |pct: f64| -> Vec<[u8; 5]> {
+        let top = ((pct / 100.0 * 2598960.0).floor() as usize).clamp(1, 2598960);
+        rank_index.as_ref().unwrap()[..top].iter().map(|&i| index_to_hand(i)).collect()
+    };
+    let pools: Vec<(f64, Vec<[u8; 5]>)> = match rank_index {
+        Some(_) => [5.0, 20.0].iter().map(|&p| (p, range_pool(p))).collect(),
+        None => Vec::new(),
+    };
+    // Every hand, so the range path runs even without a rank index.
+    let full_pool: Vec<[u8; 5]> = (0..BINOM[52][5]).map(index_to_hand).collect();
+    let saved_threads = THREAD_COUNT.load(Ordering::Relaxed);
+
+    // (case, threads, showdowns, seconds)
+    let mut rows: Vec<(String, usize, u64, f64)> = Vec::new();
+    for &threads in &thread_counts {
+        THREAD_COUNT.store(threads, Ordering::Relaxed);
+        let mut time_case = |name: &str, run: &dyn Fn() -> u64| {
+            let t = Instant::now();
+            let showdowns = run();
+            let secs = t.elapsed().as_secs_f64();
+            if !json_output {
+                eprintln!("  {:<18} {:>2} threads  {:>12.0} showdowns/s", name, threads, showdowns as f64 / secs);
+            }
+            rows.push((name.to_string(), threads, showdowns, secs));
+        };
+        let eval = evaluator.as_ref();
+        time_case("equity_preflop", &|| bench_equity(&hand, &[], &[], trials, seed, eval));
+        time_case("equity_flop", &|| bench_equity(&hand, &flop, &[], trials, seed, eval));
+        time_case("equity_turn", &|| bench_equity(&hand, &turn, &[], trials, seed, eval));
+        time_case("breakdown_river", &|| bench_breakdown(&hand, &turn, &table));
+        time_case("range_100pct", &|| bench_equity(&hand, &flop, &full_pool, trials, seed, eval));
+        for (pct, pool) in &pools {
+            time_case(&format!("range_{}pct", pct), &|| bench_equity(&hand, &flop, pool, trials, seed, eval));
         }
     }
+    THREAD_COUNT.store(saved_threads, Ordering::Relaxed);
+    if pools.is_empty() && !json_output {
+        eprintln!();
+        eprintln!("  Range cases at 5%/20% skipped: rank index '{}' not found", rank_file);
+    }
 
-    let chunk_size = (num_candidates + num_threads - 1) / num_threads;
-
-    let results: Vec<Vec<(u8, f64, u64)>> = thread::scope(|s| {
-        let handles: Vec<_> = (0..num_threads).map(|t| {
-            let table_ref = &table;
-            let hero_2s_ref = &hero_2s;
-            let board_cards_ref = &board_cards;
-            let candidate_cards_ref = &candidate_cards;
-            let rank_pool_ref = &rank_pool;
-            let use_range = is_range_restricted;
-            let scenarios_ref = &crn_scenarios;
-            s.spawn(move || {
-                let start = t * chunk_size;
-                let end = ((t + 1) * chunk_size).min(num_candidates);
-                let mut thread_results: Vec<(u8, f64, u64)> = Vec::new();
-
-                for ci in start..end {
-                    let next_card = candidate_cards_ref[ci];
-                    let next_bm = 1u64 << (next_card as u64);
-                    let mut full_board_base: Vec<u8> = board_cards_ref.to_vec();
-                    full_board_base.push(next_card);
-
-                    let combined_bm_base = hero_bm | board_bm | next_bm;
-
-                    let mut wins = 0.0f64;
-                    let mut count = 0u64;
-
-                    if is_turn_breakdown {
-                        for sc in scenarios_ref.iter() {
-                            let river_card = sc.0[0];
-                            let river_bm = 1u64 << (river_card as u64);
-                            if river_bm & combined_bm_base != 0 { continue; }
-                            let combined_bm = combined_bm_base | river_bm;
-
-                            let mut full_board = full_board_base.clone();
-                            full_board.push(river_card);
-                            full_board.sort();
-                            let board_3s = three_card_subsets_from_slice(&full_board);
-                            let hero_rank = eval_best(hero_2s_ref, &board_3s, table_ref);
-
-                            let vseed = sc.1[0];
-                            if use_range {
-                                let pool_len = rank_pool_ref.len();
-                                let mut vrng = Xorshift64::new(mix_seed(vseed));
-                                let mut found = false;
-                                let mut villain_arr = [0u8; 5];
-                                for _ in 0..200 {
-                                    let vi = vrng.gen_range(pool_len);
-                                    let cand = rank_pool_ref[vi];
-                                    let cand_bm = card_bitmap(&cand);
-                                    if cand_bm & combined_bm != 0 { continue; }
-                                    villain_arr = cand;
-                                    found = true;
-                                    break;
-                                }
-                                if !found { continue; }
-                                let villain_2s = two_card_subsets(&villain_arr);
-                                let villain_rank = eval_best(&villain_2s, &board_3s, table_ref);
-                                if hero_rank < villain_rank { wins += 1.0; }
-                                else if hero_rank == villain_rank { wins += 0.5; }
-                                count += 1;
-                            } else {
-                                let pool: Vec<u8> = (0..52u8)
-                                    .filter(|c| combined_bm & (1u64 << (*c as u64)) == 0)
-                                    .collect();
-                                let mut vrng = Xorshift64::new(mix_seed(vseed));
-                                let villain = sample_villain(&pool, &mut vrng);
-                                let villain_2s = two_card_subsets(&villain);
-                                let villain_rank = eval_best(&villain_2s, &board_3s, table_ref);
-                                if hero_rank < villain_rank { wins += 1.0; }
-                                else if hero_rank == villain_rank { wins += 0.5; }
-                                count += 1;
-                            }
-                        }
-                    } else {
-                        full_board_base.sort();
-                        let board_3s = three_card_subsets_from_slice(&full_board_base);
-                        let hero_rank = eval_best(hero_2s_ref, &board_3s, table_ref);
-
-                        for sc in scenarios_ref.iter() {
-                            let vseed = sc.1[0];
-                            if use_range {
-                                let pool_len = rank_pool_ref.len();
-                                let mut vrng = Xorshift64::new(mix_seed(vseed));
-                                let mut found = false;
-                                let mut villain_arr = [0u8; 5];
-                                for _ in 0..200 {
-                                    let vi = vrng.gen_range(pool_len);
-                                    let cand = rank_pool_ref[vi];
-                                    let cand_bm = card_bitmap(&cand);
-                                    if cand_bm & combined_bm_base != 0 { continue; }
-                                    villain_arr = cand;
-                                    found = true;
-                                    break;
-                                }
-                                if !found { continue; }
-                                let villain_2s = two_card_subsets(&villain_arr);
-                                let villain_rank = eval_best(&villain_2s, &board_3s, table_ref);
-                                if hero_rank < villain_rank { wins += 1.0; }
-                                else if hero_rank == villain_rank { wins += 0.5; }
-                                count += 1;
-                            } else {
-                                let pool: Vec<u8> = (0..52u8)
-                                    .filter(|c| combined_bm_base & (1u64 << (*c as u64)) == 0)
-                                    .collect();
-                                let mut vrng = Xorshift64::new(mix_seed(vseed));
-                                let villain = sample_villain(&pool, &mut vrng);
-                                let villain_2s = two_card_subsets(&villain);
-                                let villain_rank = eval_best(&villain_2s, &board_3s, table_ref);
-                                if hero_rank < villain_rank { wins += 1.0; }
-                                else if hero_rank == villain_rank { wins += 0.5; }
-                                count += 1;
-                            }
-                        }
-                    }
-
-                    let eq = if count > 0 { wins / count as f64 } else { 0.5 };
-                    thread_results.push((next_card, eq, count));
-                }
-                thread_results
-            })
-        }).collect();
-        handles.into_iter().map(|h| h.join().unwrap()).collect()
-    });
-
-    let mut all_results: Vec<(u8, f64, u64)> = results.into_iter().flatten().collect();
-    all_results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-
-    let elapsed = t0.elapsed().as_secs_f64();
-    let elapsed_ms = (elapsed * 1000.0) as u64;
-    let total_trials: u64 = all_results.iter().map(|r| r.2).sum();
-
-    let excluded_names: Vec<String> = excluded.iter().map(|&c| card_name(c)).collect();
+    let cases: Vec<String> = rows.iter().map(|(name, threads, showdowns, secs)| {
+        format!("{{\"name\":\"{}\",\"threads\":{},\"showdowns\":{},\"elapsedMs\":{},\"showdownsPerSec\":{:.0}}}",
+            name, threads, showdowns, (secs * 1000.0) as u64, *showdowns as f64 / secs)
+    }).collect();
+    let report = format!(
+        "{{\"ok\":true,\"version\":\"{}\",\"seed\":{},\"trials\":{},\"cpus\":{},\"timestamp\":{},\"tableBuildMs\":{},\"evalBestPerSec\":{:.0},\"rangeIndex\":{},\"cases\":[{}]}}",
+        env!("CARGO_PKG_VERSION"), seed, trials, max_threads, now_unix(), table_ms as u64, eval_rate,
+        !pools.is_empty(), cases.join(","));
+    if let Err(e) = fs::write(&out_path, format!("{}\n", report)) {
+        eprintln!("Cannot write benchmark report '{}': {}", out_path, e);
+        std::process::exit(1);
+    }
 
     if json_output {
-        print!("{{\"ok\":true,\"street\":\"{}\",\"items\":[",
-            if is_turn_breakdown { "turn" } else { "river" });
-        for (i, &(card, eq, trials)) in all_results.iter().enumerate() {
-            if i > 0 { print!(","); }
-            print!("{{\"card\":\"{}\",\"equity\":{:.6},\"trials\":{}}}",
-                card_name(card), eq, trials);
-        }
-        print!("],\"excluded\":[");
-        for (i, name) in excluded_names.iter().enumerate() {
-            if i > 0 { print!(","); }
-            print!("\"{}\"", name);
-        }
-        println!("],\"totalTrials\":{},\"trialsPerCard\":{},\"numCandidates\":{},\"seed\":{},\"elapsedMs\":{},\"villainRange\":\"{}\"}}",
-            total_trials, trials_per_card, num_candidates, seed, elapsed_ms, villain_range_str);
+        println!("{}", report);
     } else {
         eprintln!();
-        eprintln!("EQ Breakdown by next card ({}→{}):",
-            if is_turn_breakdown { "Flop" } else { "Turn" },
-            if is_turn_breakdown { "Turn" } else { "River" });
-        eprintln!("  {:>4}  {:<6}  {:>10}  {:>8}", "#", "Card", "Equity%", "Trials");
-        eprintln!("  ────  ──────  ──────────  ────────");
-        for (i, &(card, eq, trials)) in all_results.iter().enumerate() {
-            eprintln!("  {:>4}  {:<6}  {:>9.3}%  {:>8}", i + 1, card_name(card), eq * 100.0, trials);
-        }
-        eprintln!();
-        eprintln!("  Total trials: {}, Time: {:.1}s, Seed: {}", total_trials, elapsed, seed);
+        eprintln!("  Eval table build: {:.0} ms", table_ms);
+        eprintln!("  eval_best:        {:.0} showdowns/s (1 thread)", eval_rate);
+        eprintln!("  Report written to {}", out_path);
     }
 }
 
@@ -2011,7 +5909,7 @@ fn run_debug_range(args: &[String]) {
 
     let rank_index = load_rank_index(&rank_file);
     let top_k = ((villain_pct / 100.0) * 2598960.0).floor() as usize;
-    let top_k = top_k.max(1).min(2598960);
+    let top_k = top_k.clamp(1, 2598960);
 
     eprintln!("╔══════════════════════════════════════════════╗");
     eprintln!("║  Debug Range Sampling Diagnostics            ║");
@@ -2033,9 +5931,10 @@ fn run_debug_range(args: &[String]) {
     assert_eq!(rank_pool.len(), top_k, "rank_pool.len() must equal topK");
     eprintln!("  rank_pool.len() = {} ✓", rank_pool.len());
 
+    let canon_index = CanonicalIndex::build();
     let mut bucket_counts = vec![0u64; num_buckets];
-    let mut canonical_counts: HashMap<[u8; 5], u64> = HashMap::new();
-    let mut rng = Xorshift64::new(mix_seed(seed));
+    let mut canonical_counts = vec![0u64; canon_index.len()];
+    let mut rng = EngineRng::seeded(mix_seed(seed));
 
     eprintln!("  Drawing {} samples (no blocker rejection)...", samples);
     for _ in 0..samples {
@@ -2045,8 +5944,7 @@ fn run_debug_range(args: &[String]) {
         let bucket = vi * num_buckets / top_k;
         bucket_counts[bucket] += 1;
 
-        let can = canonicalize(&hand);
-        *canonical_counts.entry(can).or_insert(0) += 1;
+        canonical_counts[canon_index.id(&hand)] += 1;
     }
 
     let expected_per_bucket = samples as f64 / num_buckets as f64;
@@ -2079,14 +5977,13 @@ fn run_debug_range(args: &[String]) {
         eprintln!("  FAIL: Bucket chi2/df={:.4} >= 1.5 → NOT uniform", bucket_reduced_chi2);
     }
 
-    let mut canonical_in_topk: HashMap<[u8; 5], u32> = HashMap::new();
-    for i in 0..top_k {
-        let can = canonicalize(&rank_pool[i]);
-        *canonical_in_topk.entry(can).or_insert(0) += 1;
+    let mut canonical_in_topk = vec![0u32; canon_index.len()];
+    for hand in &rank_pool {
+        canonical_in_topk[canon_index.id(hand)] += 1;
     }
 
-    let total_canonical = canonical_counts.len();
-    let total_canonical_in_topk = canonical_in_topk.len();
+    let total_canonical = canonical_counts.iter().filter(|&&c| c > 0).count();
+    let total_canonical_in_topk = canonical_in_topk.iter().filter(|&&c| c > 0).count();
     eprintln!();
     eprintln!("  === CANONICAL CLASS DISTRIBUTION ===");
     eprintln!("  Unique canonical classes sampled:  {}", total_canonical);
@@ -2095,8 +5992,9 @@ fn run_debug_range(args: &[String]) {
     let mut max_ratio_dev = 0.0f64;
     let mut chi2 = 0.0f64;
     let mut num_checked = 0u64;
-    for (can, &expected_combos) in &canonical_in_topk {
-        let observed = *canonical_counts.get(can).unwrap_or(&0) as f64;
+    for (id, &expected_combos) in canonical_in_topk.iter().enumerate() {
+        if expected_combos == 0 { continue; }
+        let observed = canonical_counts[id] as f64;
         let expected = expected_combos as f64 / top_k as f64 * samples as f64;
         if expected < 5.0 { continue; }
         let ratio_dev = ((observed - expected) / expected).abs();
@@ -2116,24 +6014,26 @@ fn run_debug_range(args: &[String]) {
     }
 
     let top3: Vec<_> = {
-        let mut v: Vec<_> = canonical_in_topk.iter().collect();
+        let mut v: Vec<_> = canonical_in_topk.iter().enumerate()
+            .filter(|(_, &c)| c > 0)
+            .collect();
         v.sort_by(|a, b| b.1.cmp(a.1));
-        v.into_iter().take(3).map(|(can, combos)| {
-            let sampled = *canonical_counts.get(can).unwrap_or(&0);
-            let names: Vec<String> = can.iter().map(|&c| card_name(c)).collect();
+        v.into_iter().take(3).map(|(id, combos)| {
+            let sampled = canonical_counts[id];
+            let names: Vec<String> = canon_index.hands[id].0.iter().map(|&c| card_name(c)).collect();
             format!("{} combos={} sampled={} expected={:.0}",
                 names.join(""), combos, sampled,
                 *combos as f64 / top_k as f64 * samples as f64)
         }).collect()
     };
     let bot3: Vec<_> = {
-        let mut v: Vec<_> = canonical_in_topk.iter()
+        let mut v: Vec<_> = canonical_in_topk.iter().enumerate()
             .filter(|(_, &c)| c >= 4)
             .collect();
         v.sort_by(|a, b| a.1.cmp(b.1));
-        v.into_iter().take(3).map(|(can, combos)| {
-            let sampled = *canonical_counts.get(can).unwrap_or(&0);
-            let names: Vec<String> = can.iter().map(|&c| card_name(c)).collect();
+        v.into_iter().take(3).map(|(id, combos)| {
+            let sampled = canonical_counts[id];
+            let names: Vec<String> = canon_index.hands[id].0.iter().map(|&c| card_name(c)).collect();
             format!("{} combos={} sampled={} expected={:.0}",
                 names.join(""), combos, sampled,
                 *combos as f64 / top_k as f64 * samples as f64)
@@ -2172,20 +6072,54 @@ fn main() {
         eprintln!("  plo5_ranker precompute_all [options]   ALL 2,598,960 hands equity");
         eprintln!("    --boards <N>            Random boards per hero (default: 1000)");
         eprintln!("    --villain-samples <N>   Villain hands per board (default: 10)");
+        eprintln!("                            precompute: 'exact' counts every villain hand");
         eprintln!("    --seed <u64>            RNG seed (default: 12345)");
-        eprintln!("    --threads auto|<N>      Thread count (default: auto)");
+        eprintln!("    --sampling <mode>       precompute: random | stratified flops (default: random)");
+        eprintln!("    --opponents <N>         Random opponents, 1-8 (default: 1); N > 1 writes *_<N>way files");
         eprintln!("    --out-equity <path>     Output equity file (default: equity_all_2598960.f32)");
         eprintln!("    --out-rank <path>       Output rank index file (default: rank_index_all_2598960.u32)");
+        eprintln!("                            .f32/.u32 outputs get a <file>.meta sidecar with the opponent count");
+        eprintln!("    --iterate <N>           precompute_all: re-rank up to N times vs the previous top range");
+        eprintln!("    --iterate-range <N%>    Villain range for re-ranking (default: 20%)");
+        eprintln!("    --converge <rho>        Stop once Spearman vs the previous order reaches rho (default: 0.9999)");
         eprintln!();
         eprintln!("  plo5_ranker equity [options]");
         eprintln!("    --hand <hand>           Hand to evaluate (e.g., AcAdKhQh5s)");
         eprintln!("    --trials <N>            MC trials (default: 600000)");
         eprintln!("    --seed <u64>            RNG seed (default: 12345)");
+        eprintln!("    --iso                   Stratify trials over isomorphic next-street cards");
+        eprintln!("    --categories            Final hand category frequencies and win rates");
+        eprintln!("    --range-opponents <N>   Take --villain-range from the N-opponent rank index");
+        eprintln!("                            (refused if the rank file's .meta names another count)");
+        eprintln!();
+        eprintln!("  plo5_ranker flops [options]            Equity on every flop");
+        eprintln!("    --hand <hand>           Hero hand");
+        eprintln!("    --villain <hand>        Exact turn/river enumeration vs one hand");
+        eprintln!("    --villain-range <N%>    Otherwise MC vs a range (default: 100%)");
+        eprintln!("    --trials-per-flop <N>   MC trials per flop class (default: 2000)");
+        eprintln!("    --json | --csv          Machine-readable output on stdout");
+        eprintln!();
+        eprintln!("  plo5_ranker classify --hand <hand> --board <cards>   Made hand and draws");
+        eprintln!("  plo5_ranker nuts --hand <hand> --board <cards>       Nut status and what beats hero");
+        eprintln!("  plo5_ranker showdown --board <cards> --hand <hand> --villain <hand>[:...]   River showdown");
+        eprintln!("  plo5_ranker bench_eval [--showdowns N] [--seed S] [--json]   Compare Omaha evaluators");
+        eprintln!();
+        eprintln!("  plo5_ranker bench [options]            Fixed performance suite, JSON report");
+        eprintln!("    --trials <N>            Showdowns per equity case (default: 200000)");
+        eprintln!("    --threads-list <a,b>    Thread counts to run (default: 1 and auto)");
+        eprintln!("    --out <path>            Report file (default: bench.json)");
         eprintln!();
         eprintln!("  plo5_ranker accuracy [options]");
         eprintln!("    --bin <path>            Binary file to test (default: plo5_rankings_prod.bin)");
         eprintln!("    --trials <N>            MC trials per hand (default: 2000000)");
-        eprintln!("    --test-hands <list>     Comma-separated hands to test");
+        eprintln!("    --test-hands <list>     Hands to test, separated by ':' (or ',' if no ':')");
+        eprintln!();
+        eprintln!("  plo5_ranker variance [options]         Variance reduction of stratified sampling");
+        eprintln!("    --boards <N>            Precompute boards per replicate (default: 500)");
+        eprintln!("    --villain-samples <N>   Villain hands per board (default: 10)");
+        eprintln!("    --trials <N>            Equity trials per replicate (default: 10000)");
+        eprintln!("    --replicates <N>        Independent runs per estimator (default: 24)");
+        eprintln!("    --test-hands <list>     Hands to test, separated by ':' (or ',' if no ':')");
         eprintln!();
         eprintln!("  plo5_ranker baseline [options]");
         eprintln!("    --out <path>            Output file (default: baseline.json)");
@@ -2195,26 +6129,859 @@ fn main() {
         eprintln!("  plo5_ranker validate [options]");
         eprintln!("    --bin <path>            Binary rankings file");
         eprintln!("    --baseline <path>       Baseline JSON file");
+        eprintln!("    --statistical           Stratified MC check of the whole file instead of baselines");
+        eprintln!("                            (files built with --crn off or --boards full only)");
+        eprintln!("      --hands <N>           Hands to sample across rank bands (default: 200)");
+        eprintln!("      --trials <N>          MC trials per hand (default: 200000)");
+        eprintln!("      --bands <N>           Percentile bands (default: 10)");
+        eprintln!("      --alpha <p>           Significance level for pass/fail (default: 0.01)");
+        eprintln!("      --rank-file <path>    Rank index to check against the binary");
+        eprintln!();
+        eprintln!("  plo5_ranker diff_rankings --a <file> --b <file>   Compare two .bin or .f32 rankings");
+        eprintln!("    --top <N>               Largest rank moves to list (default: 20)");
+        eprintln!("    --json                  Machine-readable report on stdout");
+        eprintln!();
+        eprintln!("  plo5_ranker selftest [--deals N] [--seed S] [--json]   Check the evaluator against references");
         eprintln!();
         eprintln!("  plo5_ranker info");
         eprintln!();
+        eprintln!("  Every command accepts --threads auto|<N> (default: auto = CPUs allowed");
+        eprintln!("  by the affinity mask and cgroup CPU quota) and --rng xoshiro|legacy");
+        eprintln!("  (default: xoshiro256++; legacy restores the old Xorshift64 streams, so");
+        eprintln!("  equity and breakdown reproduce older engines; equity also needs --threads");
+        eprintln!("  set to the CPU count the old run used).");
+        eprintln!("  equity, precompute and variance also take --evaluator lookup|rank_mask");
+        eprintln!("  (default: lookup; compare them with bench_eval). precompute with CRN");
+        eprintln!("  boards always ranks through prepared pair tables and ignores it.");
+        eprintln!("  Cards are read strictly: T or 10, either case, ♣♦♥♠ suits, and spaces,");
+        eprintln!("  commas or brackets between cards are fine; anything else is an error");
+        eprintln!("  with its position. --lenient-cards restores the old skip-unknown parser.");
+        eprintln!();
         std::process::exit(0);
     }
 
+    let global = parse_flag(&args[2..], "--threads")
+        .map(|value| parse_threads(&value).map(|n| THREAD_COUNT.store(n, Ordering::Relaxed)))
+        .transpose()
+        .and_then(|_| parse_flag(&args[2..], "--rng")
+            .map(|value| parse_rng(&value).map(|legacy| LEGACY_RNG.store(legacy, Ordering::Relaxed)))
+            .transpose())
+        .and_then(|_| parse_flag(&args[2..], "--evaluator")
+            .map(|value| parse_evaluator(&value).map(|mask| RANK_MASK_EVAL.store(mask, Ordering::Relaxed)))
+            .transpose());
+    LENIENT_CARDS.store(args.iter().any(|a| a == "--lenient-cards"), Ordering::Relaxed);
+    if let Err(msg) = global {
+        fail(args.iter().any(|a| a == "--json"), msg);
+    }
+
     match args[1].as_str() {
         "precompute" => run_precompute(&args[2..]),
         "precompute_all" => run_precompute_all(&args[2..]),
         "build_rank_index" => run_build_rank_index(&args[2..]),
         "equity" => run_equity(&args[2..]),
         "breakdown" => run_breakdown(&args[2..]),
+        "flops" => run_flops(&args[2..]),
+        "classify" => run_classify(&args[2..]),
+        "nuts" => run_nuts(&args[2..]),
+        "showdown" => run_showdown(&args[2..]),
+        "bench_eval" | "bench-eval" => run_bench_eval(&args[2..]),
+        "bench" => run_bench(&args[2..]),
         "accuracy" => run_accuracy(&args[2..]),
+        "variance" => run_variance(&args[2..]),
         "baseline" => run_baseline(&args[2..]),
         "validate" => run_validate(&args[2..]),
+        "diff_rankings" | "diff-rankings" => run_diff_rankings(&args[2..]),
         "debug_range" | "debug-range" => run_debug_range(&args[2..]),
+        "selftest" => run_selftest(&args[2..]),
         "info" => run_info(),
         other => {
-            eprintln!("Unknown command: {}. Use precompute, precompute_all, build_rank_index, equity, breakdown, accuracy, baseline, validate, debug_range, or info.", other);
+            eprintln!("Unknown command: {}. Use precompute, precompute_all, build_rank_index, equity, breakdown, flops, classify, nuts, showdown, bench, bench_eval, accuracy, variance, baseline, validate, diff_rankings, debug_range, selftest, or info.", other);
             std::process::exit(1);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonicalize_brute(cards: &[u8; 5]) -> [u8; 5] {
+        SUIT_PERMS.iter().map(|perm| apply_suit_perm(cards, perm)).min().unwrap()
+    }
+
+    fn canonicalize_pair_brute<const B: usize>(hand: &[u8; 5], board: &[u8; B]) -> ([u8; 5], [u8; B]) {
+        SUIT_PERMS.iter()
+            .map(|perm| (apply_suit_perm(hand, perm), apply_suit_perm(board, perm)))
+            .min()
+            .unwrap()
+    }
+
+    fn random_deal<const B: usize>(rng: &mut Xorshift64) -> ([u8; 5], [u8; B]) {
+        let all52: Vec<u8> = (0..52u8).collect();
+        let cards = sample_n(&all52, 5 + B, rng);
+        let mut hand = [0u8; 5];
+        hand.copy_from_slice(&cards[..5]);
+        hand.sort();
+        let mut board = [0u8; B];
+        board.copy_from_slice(&cards[5..]);
+        (hand, board)
+    }
+
+    fn eval_table() -> &'static [u16] {
+        static TABLE: OnceLock<Vec<u16>> = OnceLock::new();
+        TABLE.get_or_init(init_eval_table)
+    }
+
+    #[test]
+    fn comb_index_round_trips_every_index() {
+        for idx in 0..BINOM[52][5] {
+            let hand = index_to_hand(idx);
+            assert!(hand.windows(2).all(|w| w[0] < w[1]) && hand[4] < 52, "index {} gave {:?}", idx, hand);
+            assert_eq!(comb_index(&hand) as u32, idx);
+        }
+        assert_eq!(index_to_hand(0), [0, 1, 2, 3, 4]);
+        assert_eq!(index_to_hand(BINOM[52][5] - 1), [47, 48, 49, 50, 51]);
+    }
+
+    #[test]
+    fn canonicalize_is_idempotent_and_suit_invariant() {
+        for idx in (0..BINOM[52][5]).step_by(61) {
+            let hand = index_to_hand(idx);
+            let canonical = canonicalize(&hand);
+            assert_eq!(canonicalize(&canonical), canonical);
+            for perm in &SUIT_PERMS {
+                let relabelled = apply_suit_perm(&hand, perm);
+                assert_eq!(canonicalize(&relabelled), canonical, "{:?} under {:?}", hand, perm);
+            }
+        }
+    }
+
+    #[test]
+    fn parse_cards_vec_reads_ten_notation() {
+        assert_eq!(parse_cards_vec("10h5d7c"), parse_cards_vec("Th5d7c"));
+        assert_eq!(parse_cards_vec("10h"), vec![2 * 13 + 8]);
+        assert_eq!(parse_cards_vec("Ts10s"), vec![3 * 13 + 8, 3 * 13 + 8]);
+        assert_eq!(parse_hand("10cJcQcKcAc"), parse_hand("TcJcQcKcAc"));
+        assert_eq!(parse_cards_vec("AcAdKhQh5s"), vec![12, 25, 37, 36, 42]);
+        // Unknown tokens are still skipped by this lenient parser.
+        assert_eq!(parse_cards_vec("1h"), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn strict_parser_accepts_notation_variants() {
+        let expected = parse_cards_vec("AcTd9h2s");
+        for s in ["AcTd9h2s", "ac td 9H 2S", "A♣10♦9♥2♠", "[Ac, 10d, 9h, 2s]", "(Ac-Td/9h|2s)", "A♣️T♦️9♥️2♠️"] {
+            assert_eq!(parse_cards_strict(s), Ok(expected.clone()), "{}", s);
+        }
+        assert_eq!(parse_cards_strict("  "), Ok(Vec::new()));
+        assert_eq!(parse_hand("kd qd jd td 10d").unwrap_err(), "Duplicate card Td at position 13");
+    }
+
+    #[test]
+    fn strict_parser_reports_positions() {
+        assert_eq!(parse_cards_strict("AcAdXx5s").unwrap_err(), "Invalid rank 'X' at position 5");
+        assert_eq!(parse_cards_strict("Ac Az").unwrap_err(),
+            "Invalid suit 'z' at position 5 (expected c, d, h, s or ♣♦♥♠)");
+        assert_eq!(parse_cards_strict("AcK").unwrap_err(), "Missing suit after rank 'K' at position 3");
+        assert_eq!(parse_cards_strict("1h").unwrap_err(), "Invalid rank '1' at position 1");
+        assert_eq!(parse_cards_strict("♠A").unwrap_err(), "Invalid rank '♠' at position 1");
+        assert_eq!(parse_cards_strict("A\"").unwrap_err(),
+            "Invalid suit '\\\"' at position 2 (expected c, d, h, s or ♣♦♥♠)");
+        assert_eq!(parse_hand("AcAdKh").unwrap_err(), "need exactly 5 cards, got 3");
+        // The lenient parser still skips what it does not understand.
+        assert_eq!(parse_cards_vec("AcAdXx5s").len(), 3);
+    }
+
+    #[test]
+    fn hand_lists_and_json_errors_quote_input_safely() {
+        // Commas are card separators, so hand lists split on a character the
+        // strict parser rejects.
+        assert_eq!(parse_cards_strict("AcAdKh,Qs2c").map(|c| c.len()), Ok(5));
+        assert!(parse_cards_strict(&HAND_LIST_SEPARATOR.to_string()).is_err());
+        let args = ["--test-hands", "Ac,Ad,Kh,Qs,2c: 7h6h5d4c3c"].map(String::from);
+        assert_eq!(parse_test_hands(&args), ["Ac,Ad,Kh,Qs,2c", "7h6h5d4c3c"]);
+        let legacy = ["--test-hands", "JsTh5dTc4c,AcAhTh8d7c"].map(String::from);
+        assert_eq!(parse_test_hands(&legacy), ["JsTh5dTc4c", "AcAhTh8d7c"]);
+        assert_eq!(json_escape("bad \"x\\y\"\n\u{1}"), "bad \\\"x\\\\y\\\"\\n\\u0001");
+    }
+
+    #[test]
+    fn merge5_merges_sorted_parts() {
+        let mut out = [0u8; 5];
+        merge5(&[2, 9], &[1, 5, 30], &mut out);
+        assert_eq!(out, [1, 2, 5, 9, 30]);
+        merge5(&[40, 51], &[0, 1, 2], &mut out);
+        assert_eq!(out, [0, 1, 2, 40, 51]);
+        let mut rng = Xorshift64::new(mix_seed(3));
+        let deck: Vec<u8> = (0..52u8).collect();
+        for _ in 0..10_000 {
+            let cards = sample_n(&deck, 5, &mut rng);
+            let (mut a, mut b) = ([cards[0], cards[1]], [cards[2], cards[3], cards[4]]);
+            a.sort();
+            b.sort();
+            let mut expected: [u8; 5] = cards.try_into().unwrap();
+            expected.sort();
+            merge5(&a, &b, &mut out);
+            assert_eq!(out, expected);
+        }
+    }
+
+    #[test]
+    fn three_card_subsets_from_slice_handles_short_boards() {
+        let flop = three_card_subsets_from_slice(&[4, 17, 30]);
+        assert!(flop.iter().all(|s| *s == [4, 17, 30]));
+
+        let turn = three_card_subsets_from_slice(&[4, 17, 30, 43]);
+        let distinct: HashSet<[u8; 3]> = turn.iter().copied().collect();
+        assert_eq!(distinct.len(), 4);
+        assert_eq!(turn[..4], [[4, 17, 30], [4, 17, 43], [4, 30, 43], [17, 30, 43]]);
+        assert!(turn[4..].iter().all(|s| *s == turn[0]));
+
+        let river = [4, 17, 30, 43, 50];
+        assert_eq!(three_card_subsets_from_slice(&river), three_card_subsets(&river));
+    }
+
+    #[test]
+    fn sample_villain_is_uniform() {
+        // All C(8,5) = 56 holdings from an 8-card pool should be equally likely.
+        let pool: Vec<u8> = (20..28u8).collect();
+        let draws = 56 * 2000;
+        let chi2 = |rng: &mut dyn FnMut() -> [u8; 5]| {
+            let mut counts: HashMap<[u8; 5], u32> = HashMap::new();
+            for _ in 0..draws {
+                let mut villain = rng();
+                assert!(villain.iter().all(|c| pool.contains(c)));
+                villain.sort();
+                assert!(villain.windows(2).all(|w| w[0] < w[1]));
+                *counts.entry(villain).or_default() += 1;
+            }
+            assert_eq!(counts.len(), 56);
+            let expected = draws as f64 / 56.0;
+            counts.values().map(|&n| (n as f64 - expected).powi(2) / expected).sum::<f64>()
+        };
+        // 55 degrees of freedom: p < 0.0001 above ~100.
+        let mut legacy = Xorshift64::new(mix_seed(11));
+        assert!(chi2(&mut || sample_villain(&pool, &mut legacy)) < 100.0);
+        let mut xoshiro = Xoshiro256pp::from_seed(11);
+        assert!(chi2(&mut || sample_villain(&pool, &mut xoshiro)) < 100.0);
+    }
+
+    #[test]
+    fn canonicalize_matches_brute_force_on_every_hand() {
+        for idx in 0..BINOM[52][5] {
+            let hand = index_to_hand(idx);
+            assert_eq!(canonicalize(&hand), canonicalize_brute(&hand), "hand index {}", idx);
+        }
+    }
+
+    #[test]
+    fn board_class_key_matches_brute_force() {
+        let mut rng = Xorshift64::new(mix_seed(7));
+        for _ in 0..20_000 {
+            let (hand, flop) = random_deal::<3>(&mut rng);
+            let brute = subset_index(&canonicalize_pair_brute(&hand, &flop).1);
+            assert_eq!(board_class_key(&[&hand], &flop), brute);
+            let (hand, turn) = random_deal::<4>(&mut rng);
+            let brute = subset_index(&canonicalize_pair_brute(&hand, &turn).1);
+            assert_eq!(board_class_key(&[&hand], &turn), brute);
+            let (hand, river) = random_deal::<5>(&mut rng);
+            let brute = subset_index(&canonicalize_pair_brute(&hand, &river).1);
+            assert_eq!(board_class_key(&[&hand], &river), brute);
+        }
+    }
+
+    #[test]
+    fn canonical_index_is_dense_and_consistent() {
+        let index = CanonicalIndex::build();
+        assert_eq!(index.len(), 134_459);
+        let total: u64 = index.hands.iter().map(|&(_, c)| c as u64).sum();
+        assert_eq!(total, 2_598_960);
+        assert!(index.hands.windows(2).all(|w| w[0].0 < w[1].0));
+        for (id, (hand, _)) in index.hands.iter().enumerate() {
+            assert_eq!(canonicalize(hand), *hand);
+            assert_eq!(index.id(hand), id);
+        }
+        for idx in (0..BINOM[52][5]).step_by(97) {
+            let hand = index_to_hand(idx);
+            assert_eq!(index.hands[index.id(&hand)].0, canonicalize(&hand));
+        }
+    }
+
+    #[test]
+    fn pair_index_is_dense_and_consistent() {
+        // Each hand's ids fill its own range exactly, one per board class.
+        fn check_hand<const B: usize>(pairs: &PairIndex<B>, id: usize, hand: &[u8; 5]) {
+            let classes = board_classes::<B>(&[hand]);
+            let mut ids: HashMap<u64, usize> = HashMap::new();
+            let mut idx: [usize; B] = std::array::from_fn(|i| i);
+            let pool: Vec<u8> = (0..52u8).filter(|c| !hand.contains(c)).collect();
+            'boards: loop {
+                let board: [u8; B] = idx.map(|i| pool[i]);
+                let key = board_class_key(&[hand], &board);
+                assert_eq!(*ids.entry(pairs.id(hand, &board)).or_insert(key), key);
+                let mut i = B;
+                loop {
+                    if i == 0 { break 'boards; }
+                    i -= 1;
+                    if idx[i] < pool.len() - B + i { break; }
+                }
+                idx[i] += 1;
+                for j in (i + 1)..B { idx[j] = idx[j - 1] + 1; }
+            }
+            let mut ids: Vec<u64> = ids.into_keys().collect();
+            ids.sort();
+            assert_eq!(ids.len(), classes.len());
+            assert_eq!(ids, (pairs.offsets[id]..pairs.offsets[id + 1]).collect::<Vec<u64>>());
+        }
+
+        let index = CanonicalIndex::build();
+        let turns = PairIndex::<1>::build(&index);
+        for (id, (hand, _)) in index.hands.iter().enumerate().step_by(31) {
+            check_hand(&turns, id, hand);
+        }
+        let flops = PairIndex::<3>::build(&index);
+        let symmetric = parse_hand("AcAdKcKd5h").unwrap();
+        check_hand(&flops, index.id(&symmetric), &symmetric);
+        for (id, (hand, _)) in index.hands.iter().enumerate().step_by(4001) {
+            check_hand(&flops, id, hand);
+        }
+
+        // Relabelling suits keeps the id; other classes get other ids.
+        let mut rng = Xorshift64::new(mix_seed(11));
+        for _ in 0..2_000 {
+            let (hand, flop) = random_deal::<3>(&mut rng);
+            let id = flops.id(&hand, &flop);
+            assert!(id < flops.len());
+            for perm in &SUIT_PERMS {
+                assert_eq!(flops.id(&apply_suit_perm(&hand, perm), &apply_suit_perm(&flop, perm)), id);
+            }
+            let (other_hand, other_flop) = random_deal::<3>(&mut rng);
+            let same_class = canonicalize_pair_brute(&hand, &flop) == canonicalize_pair_brute(&other_hand, &other_flop);
+            assert_eq!(flops.id(&other_hand, &other_flop) == id, same_class);
+        }
+    }
+
+    #[test]
+    fn board_classes_cover_every_board_once() {
+        let hand = parse_hand("AcAdKcKd5h").unwrap();
+        let flops = board_classes::<3>(&[&hand]);
+        let total: u32 = flops.iter().map(|&(_, w)| w).sum();
+        assert_eq!(total, BINOM[47][3]);
+        // Only the clubs/diamonds swap fixes this hand.
+        assert!(flops.len() > BINOM[47][3] as usize / 2 && flops.len() < BINOM[47][3] as usize);
+        let mut keys: Vec<usize> = flops.iter().map(|(b, _)| board_class_key(&[&hand], b)).collect();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), flops.len());
+
+        let asymmetric = parse_hand("AcKdQhJs9s").unwrap();
+        assert_eq!(board_classes::<3>(&[&asymmetric]).len(), BINOM[47][3] as usize);
+
+        let board = parse_cards_vec("7h8h9s");
+        let turns = board_classes::<1>(&[&hand, &board]);
+        assert_eq!(turns.iter().map(|&(_, w)| w).sum::<u32>(), 44);
+        assert_eq!(turns.len(), 33);
+    }
+
+    #[test]
+    fn flop_texture_flags() {
+        let tx = |s: &str| {
+            let c = parse_cards_vec(s);
+            flop_texture(&[c[0], c[1], c[2]])
+        };
+        assert!(tx("2h7h9h").monotone && !tx("2h7h9h").connected);
+        assert!(tx("5c5dKh").paired && !tx("5c5dKh").connected);
+        assert!(tx("9cTdQh").connected);
+        assert!(tx("Ac2d4h").connected);
+        assert!(!tx("Ac5d6h").connected);
+    }
+
+    #[test]
+    fn next_card_groups_follow_precedence() {
+        let hand = parse_hand("AcAdKcQh5s").unwrap();
+        let board = parse_cards_vec("Jh9c3c");
+        let group = |c: &str| next_card_group(&hand, &board, parse_cards_vec(c)[0]);
+        assert!(group("2c") == NextCardGroup::Flush);
+        // Flush and straight at once counts as the flush.
+        assert!(group("Tc") == NextCardGroup::Flush);
+        assert!(group("Ts") == NextCardGroup::Straight);
+        assert!(group("9d") == NextCardGroup::BoardPair);
+        assert!(group("Kd") == NextCardGroup::Overcard);
+        assert!(group("2d") == NextCardGroup::Blank);
+        // Straight cards that hero cannot use are not "straight".
+        assert!(group("8s") == NextCardGroup::Blank);
+
+        // A third club gives this hand (one club) no flush.
+        let no_clubs = parse_hand("AdAhKhQs5s").unwrap();
+        assert!(next_card_group(&no_clubs, &board, parse_cards_vec("2c")[0]) == NextCardGroup::Blank);
+    }
+
+    #[test]
+    fn classify_respects_exactly_two_hole_cards() {
+        let cards = |s: &str| parse_cards_vec(s);
+        // One heart in hand never makes a flush, even on four hearts.
+        let c = classify_hand(&parse_hand("AhKcQd7s2c").unwrap(), &cards("Jh8h5h3h"), &[]);
+        assert_eq!(c.category, 8);
+        assert!(c.flush_outs.is_empty());
+
+        // Nut flush draw with a 17-out wrap on 9-8-x.
+        let c = classify_hand(&parse_hand("AhKhJcTc7d").unwrap(), &cards("9h8s2h"), &[]);
+        assert_eq!(c.flush_outs.len(), 9);
+        assert!(c.nut_flush_draw);
+        assert_eq!(c.straight_draw(), Some("wrap"));
+        assert_eq!(c.straight_outs.len(), 17);
+
+        // Pocket nines on a dry flop: one set out once the dead 9s is removed.
+        let c = classify_hand(&parse_hand("9c9dKs5h3c").unwrap(), &cards("Ad7h2c"), &cards("9s"));
+        assert_eq!(c.category, 7);
+        assert_eq!(c.set_outs, cards("9h"));
+        assert!(c.backdoor_flush);
+    }
+
+    #[test]
+    fn nut_status_ranks_hero_among_two_card_holdings() {
+        let table = eval_table();
+        let board = parse_cards_vec("7d2sAh");
+        let unseen_for = |hand: &[u8; 5]| -> Vec<u8> {
+            let used = card_bitmap(hand) | card_bitmap(&board);
+            (0..52u8).filter(|&c| used & (1u64 << c) == 0).collect()
+        };
+
+        let aces = parse_hand("AcAdQsJs3c").unwrap();
+        let status = nut_status(&aces, &board, &unseen_for(&aces), table);
+        assert_eq!(status.position, 1);
+        assert!(status.better.is_empty());
+        assert_eq!(status.total_combos, 44 * 43 / 2);
+
+        // Kings lose to every ace, so top set is the best beater.
+        let kings = parse_hand("KcKdQsJs3c").unwrap();
+        let status = nut_status(&kings, &board, &unseen_for(&kings), table);
+        assert!(status.position > 1);
+        assert!(status.better[0].0.iter().all(|&c| c % 13 == 12));
+        let (prob, runouts) = nut_probability(&kings, &board, &unseen_for(&kings), table);
+        assert_eq!(runouts, 44 * 43 / 2);
+        assert!(prob > 0.0 && prob < 0.1);
+    }
+
+    #[test]
+    fn rank_to_category_matches_sort_key() {
+        let table = eval_table();
+        for idx in 0..BINOM[52][5] {
+            let hand = index_to_hand(idx);
+            let cat = (hand_sort_key(&hand) / 14u64.pow(5)) as u8;
+            assert_eq!(rank_to_category(table[idx as usize]), cat, "{:?}", hand);
+        }
+    }
+
+    #[test]
+    fn describe_hand_names_every_category() {
+        let describe = |s: &str| describe_hand(&parse_hand(s).unwrap());
+        assert_eq!(describe("AhKhQhJhTh"), "Royal flush");
+        assert_eq!(describe("5d4d3d2dAd"), "Five-high straight flush");
+        assert_eq!(describe("6c6d6h6s2c"), "Four Sixes");
+        assert_eq!(describe("KcKdKs7h7c"), "Kings full of Sevens");
+        assert_eq!(describe("Ah9h7h4h2h"), "Ace-high flush");
+        assert_eq!(describe("Ac2d3h4s5c"), "Five-high straight");
+        assert_eq!(describe("QcQdQh9s2c"), "Three Queens");
+        assert_eq!(describe("AcAd8h8s2c"), "Aces and Eights");
+        assert_eq!(describe("JcJd9h5s2c"), "Pair of Jacks");
+        assert_eq!(describe("AcKd9h5s2c"), "Ace high");
+    }
+
+    #[test]
+    fn best_hand_uses_exactly_two_hole_cards() {
+        let table = eval_table();
+        let board: [u8; 5] = parse_hand("Ah2h3h4d9c").unwrap();
+        let (rank, hole, used) = best_hand(&parse_hand("5h6hKcQdJd").unwrap(), &board, table);
+        assert_eq!(rank_to_category(rank), 3);
+        assert_eq!(hole.to_vec(), parse_cards_vec("5h6h"));
+        assert!(used.iter().all(|c| c / 13 == 2));
+    }
+
+    #[test]
+    fn rank_mask_evaluator_matches_lookup() {
+        let table = eval_table();
+        let lookup = LookupEvaluator { table };
+        let rank_mask = RankMaskEvaluator::new(table);
+        let mut rng = Xorshift64::new(mix_seed(7));
+        // Half the deals draw from two suits only, so flushes are common.
+        let full: Vec<u8> = (0..52).collect();
+        let two_suits: Vec<u8> = (0..26).collect();
+        for i in 0..20_000 {
+            let deck = if i % 2 == 0 { &full } else { &two_suits };
+            let cards = sample_n(deck, 10, &mut rng);
+            let mut hand: [u8; 5] = cards[..5].try_into().unwrap();
+            let mut board: [u8; 5] = cards[5..].try_into().unwrap();
+            hand.sort();
+            board.sort();
+            assert_eq!(rank_mask.best_rank(&hand, &board), lookup.best_rank(&hand, &board),
+                "{:?} on {:?}", hand, board);
+        }
+    }
+
+    #[test]
+    fn parallel_map_keeps_task_order() {
+        let squares = parallel_map(1000, |i| i * i);
+        assert!(squares.iter().enumerate().all(|(i, &sq)| sq == i * i));
+        assert_eq!(parse_threads("auto"), Ok(0));
+        assert_eq!(parse_threads("3"), Ok(3));
+        assert!(parse_threads("0").is_err());
+    }
+
+    #[test]
+    fn proc_cgroup_paths_resolve_both_hierarchies() {
+        let hybrid = "12:cpu,cpuacct:/docker/abc\n4:memory:/docker/abc\n0::/system.slice/x.scope\n";
+        assert_eq!(parse_proc_cgroup(hybrid),
+            (Some("/system.slice/x.scope".into()), Some("/docker/abc".into())));
+        assert_eq!(parse_proc_cgroup("1:cpuacct:/\n"), (None, None));
+        assert_eq!(cgroup_ancestors("/sys/fs/cgroup", Some("/a/b/")),
+            ["/sys/fs/cgroup/a/b", "/sys/fs/cgroup/a", "/sys/fs/cgroup"]);
+        assert_eq!(cgroup_ancestors("/sys/fs/cgroup/cpu", Some("/")), ["/sys/fs/cgroup/cpu"]);
+    }
+
+    #[test]
+    fn xoshiro_streams_and_bounded_sampling() {
+        let mut reference = Xoshiro256pp { s: [1, 2, 3, 4] };
+        assert_eq!(reference.next_u64(), 41943041);
+
+        struct Scripted(Vec<u64>);
+        impl RandomSource for Scripted {
+            fn next_u64(&mut self) -> u64 { self.0.remove(0) }
+        }
+        // 0 lands in the biased low zone for n = 3 and must be redrawn.
+        assert_eq!(Scripted(vec![0, u64::MAX]).gen_range(3), 2);
+
+        let mut base = Xoshiro256pp::from_seed(9);
+        let mut first = base.clone();
+        base.jump();
+        let a: Vec<u64> = (0..8).map(|_| first.next_u64()).collect();
+        let b: Vec<u64> = (0..8).map(|_| base.next_u64()).collect();
+        assert!(a.iter().all(|x| !b.contains(x)));
+
+        let mut rng = Xoshiro256pp::from_seed(5);
+        let mut counts = [0u32; 6];
+        for _ in 0..60_000 { counts[rng.gen_range(6)] += 1; }
+        assert!(counts.iter().all(|&c| (9_500..10_500).contains(&c)), "{:?}", counts);
+    }
+
+    #[test]
+    fn stratified_flops_cover_every_live_flop() {
+        let order = flop_strata_order();
+        assert_eq!(order.len(), 22100);
+        let hero = parse_hand("AcAdKhQh5s").unwrap();
+        let hero_bm = card_bitmap(&hero);
+        let mut rng = Xorshift64::new(mix_seed(3));
+        let mut flops = stratified_flops(&order, hero_bm, 16215, &mut rng);
+        assert!(flops.iter().all(|f| card_bitmap(f) & hero_bm == 0));
+        flops.sort();
+        flops.dedup();
+        assert_eq!(flops.len(), 16215);
+        assert_eq!(hero_flop_order(&hero, eval_table()).len(), 16215);
+    }
+
+    #[test]
+    fn river_villain_counts_match_enumeration() {
+        let table = eval_table();
+        let board = parse_hand("2d5h9hJsKh").unwrap();
+        let prepared = PreparedBoard::new(&board, table);
+        let counts = RiverVillainCounts::new(&board, &prepared);
+        for hand in ["AhQh7c6c3s", "TcTd8s7d6d"] {
+            let hero = parse_hand(hand).unwrap();
+            let hero_rank = prepared.best_rank(&hero);
+            let pool: Vec<u8> = (0..52u8).filter(|c| !board.contains(c) && !hero.contains(c)).collect();
+            let (wins, ties, total) = counts.outcomes(&hero, hero_rank);
+            assert_eq!((wins as f64 + 0.5 * ties as f64, total),
+                exact_villain_outcomes(hero_rank, &pool, &prepared), "{}", hand);
+        }
+    }
+
+    #[test]
+    fn multiway_share_splits_the_pot() {
+        let pool: Vec<u8> = (10..52u8).collect();
+        let mut rng = Xorshift64::new(mix_seed(5));
+        assert_eq!(multiway_share(100, &pool, 1, &mut rng, |_| 100), 0.5);
+        assert_eq!(multiway_share(100, &pool, 3, &mut rng, |_| 100), 0.25);
+        assert_eq!(multiway_share(100, &pool, 5, &mut rng, |_| 200), 1.0);
+        // Five opponents take all 25 cards, so someone holds the beater.
+        let exact_pool: Vec<u8> = (27..52u8).collect();
+        assert_eq!(multiway_share(100, &exact_pool, 5, &mut rng, |v| if v.contains(&51) { 50 } else { 200 }), 0.0);
+    }
+
+    #[test]
+    fn multiway_outputs_record_their_opponents() {
+        assert_eq!(multiway_path("public/rank_index_all_2598960.u32", 1), "public/rank_index_all_2598960.u32");
+        assert_eq!(multiway_path("public/rank_index_all_2598960.u32", 4), "public/rank_index_all_2598960_4way.u32");
+
+        let rank_file = std::env::temp_dir().join(format!("plo5_meta_test_{}.u32", std::process::id()));
+        let rank_file = rank_file.to_str().unwrap();
+        assert_eq!(read_opponents_meta(rank_file), None);
+        write_opponents_meta(rank_file, 3);
+        assert_eq!(read_opponents_meta(rank_file), Some(3));
+        let args = |n: &str| ["--rank-file", rank_file, "--range-opponents", n].map(String::from);
+        assert!(range_files(&args("3")).is_ok());
+        assert!(range_files(&args("1")).is_err());
+        fs::remove_file(meta_path(rank_file)).unwrap();
+    }
+
+    #[test]
+    fn iterative_ranking_helpers() {
+        let order: Vec<u32> = (0..100).collect();
+        let reversed: Vec<u32> = (0..100).rev().collect();
+        assert_eq!(spearman(&order, &order), 1.0);
+        assert!((spearman(&order, &reversed) + 1.0).abs() < 1e-12);
+        assert_eq!(range_overlap(&order[..10], &reversed[90..]), 1.0);
+        assert_eq!(range_overlap(&order[..10], &reversed[..10]), 0.0);
+        assert_eq!(rank_order(&[0.2, 0.9, 0.5]), vec![1, 2, 0]);
+
+        // Only two range hands avoid the blocked cards, and the second one
+        // must also avoid the first.
+        let range = [[0, 1, 2, 3, 4], [5, 6, 7, 8, 9], [0, 5, 10, 11, 12], [13, 14, 15, 16, 17]];
+        let mut rng = Xorshift64::new(mix_seed(9));
+        let blocked = card_bitmap(&[10, 13]);
+        assert_eq!(range_share(100, blocked, &range, 2, &mut rng, |_| 100), Some(1.0 / 3.0));
+        assert_eq!(range_share(100, blocked, &range, 3, &mut rng, |_| 200), None);
+    }
+
+    #[test]
+    fn with_suffix_goes_before_the_extension() {
+        assert_eq!(with_suffix("out/rank.u32", "_iter2"), "out/rank_iter2.u32");
+        assert_eq!(with_suffix("./rank", "_iter2"), "./rank_iter2");
+        assert_eq!(with_suffix(".hidden", "_iter2"), ".hidden_iter2");
+    }
+
+    #[test]
+    fn diff_rankings_reports_moves_and_boundaries() {
+        // Ten classes of ten combos each; the second file swaps the top and
+        // the 6th hand and adds a uniform 0.01 shift.
+        let a: Vec<f64> = (0..10).map(|i| 0.9 - i as f64 * 0.05).collect();
+        let mut b: Vec<f64> = a.iter().map(|e| e + 0.01).collect();
+        b.swap(0, 5);
+        let diff = diff_rankings(&a, &b, &[10; 10]);
+        assert_eq!(diff.moves.len(), 2);
+        assert_eq!(diff.rank_a[0], 0);
+        assert_eq!(diff.rank_b[0], 5);
+        assert!((diff.max_delta - 0.26).abs() < 1e-9);
+        assert!(diff.spearman < 1.0);
+
+        let top10 = &diff.boundaries[1];
+        assert_eq!(top10.pct, 10.0);
+        assert_eq!((top10.entering, top10.leaving), (10, 10));
+        assert!((top10.equity_a - 0.9).abs() < 1e-9);
+        let top50 = &diff.boundaries[3];
+        assert_eq!((top50.entering, top50.leaving), (10, 10));
+
+        let same = diff_rankings(&a, &a, &[10; 10]);
+        assert!(same.moves.is_empty());
+        assert_eq!(same.spearman, 1.0);
+        assert_eq!(same.max_delta, 0.0);
+    }
+
+    #[test]
+    fn validation_statistics() {
+        assert!((normal_cdf(0.0) - 0.5).abs() < 1e-7);
+        assert!((normal_cdf(1.96) - 0.975).abs() < 1e-4);
+        assert!((normal_quantile(0.975) - 1.96).abs() < 1e-3);
+        assert!((normal_quantile(0.005) + 2.5758).abs() < 1e-3);
+        // Chi-square with 100 df: median ~99.3, 150 is far in the tail.
+        assert!((chi_square_p(99.334, 100) - 0.5).abs() < 0.01);
+        assert!(chi_square_p(150.0, 100) < 0.002);
+        assert!(chi_square_p(60.0, 100) > 0.99);
+    }
+
+    #[test]
+    fn eval_table_matches_documented_counts() {
+        for check in eval_table_checks(eval_table()) {
+            assert!(check.pass, "{}: {}", check.name, check.detail);
+        }
+    }
+
+    #[test]
+    fn eval_best_matches_brute_force_reference() {
+        let wheel = reference_hand_value(&parse_hand("Ac2s3h4d5c").unwrap());
+        let six_high = reference_hand_value(&parse_hand("2s3h4d5c6c").unwrap());
+        assert_eq!(wheel, (4, [3, 0, 0, 0, 0]));
+        assert!(wheel < six_high);
+        assert_eq!(reference_hand_value(&parse_hand("KcKdKh7s7c").unwrap()), (6, [11, 11, 11, 5, 5]));
+
+        let check = omaha_reference_check(eval_table(), 3000, 1);
+        assert!(check.pass, "{}", check.detail);
+        // A table that ties everything must be caught.
+        assert!(!omaha_reference_check(&vec![0u16; 2598960], 200, 1).pass);
+    }
+
+    #[test]
+    fn equity_trials_ignore_thread_count() {
+        let table = eval_table();
+        let hand = parse_hand("AcAdKhQh5s").unwrap();
+        let board = parse_cards_vec("Jh8h3c");
+        let excluded_bm = card_bitmap(&hand) | card_bitmap(&board);
+        let remaining: Vec<u8> = (0..52u8).filter(|&c| excluded_bm & (1u64 << c) == 0).collect();
+        let strata = [Stratum { cards: Vec::new(), end: 3 * TRIAL_BLOCK + 100, weight: 1.0 }];
+        let lookup = LookupEvaluator { table };
+        let setup = EquitySetup {
+            hand,
+            board_cards: &board,
+            excluded_bm,
+            remaining: &remaining,
+            strata: &strata,
+            rank_pool: &[],
+            board_to_fill: 2,
+            seed: 42,
+            evaluator: &lookup,
+            threads: 1,
+            legacy_rng: false,
+        };
+        let run = |threads: usize| {
+            let t = equity_trials(&EquitySetup { threads, ..setup });
+            (t.wins, t.ties, t.total, t.weighted.0.to_bits(), t.weighted.2.to_bits())
+        };
+        let single = run(1);
+        let many = run(4);
+        assert_eq!(single, many);
+        assert_eq!(single.2, 3 * TRIAL_BLOCK as u64 + 100);
+    }
+
+    #[test]
+    fn iso_strata_match_plain_sampling_with_a_range() {
+        let table = eval_table();
+        let hand = parse_hand("AcAdKhQh5s").unwrap();
+        let board = parse_cards_vec("Jh8h3c");
+        // A range made mostly of hearts leaves hero's flush card on the turn
+        // far less often than a random villain would; weighting the classes
+        // by card counts alone puts iso about three points too high.
+        let range_cards = parse_cards_vec("2h3h4h6h7h9hTh2d4d6d");
+        let mut rank_pool: Vec<[u8; 5]> = Vec::new();
+        for_each_subset(&range_cards, 5, |h| rank_pool.push(h.try_into().unwrap()));
+
+        let excluded_bm = card_bitmap(&hand) | card_bitmap(&board);
+        let blockers = RangeBlockers::new(&rank_pool, excluded_bm, 3);
+        let unseen: Vec<u8> = (0..52u8).filter(|&c| excluded_bm & (1u64 << c) == 0).collect();
+        let mut rng = Xorshift64::new(mix_seed(42));
+        for _ in 0..200 {
+            let mut cards = sample_n(&unseen, 3, &mut rng);
+            cards.sort();
+            let brute = rank_pool.iter()
+                .filter(|h| card_bitmap(&h[..]) & (excluded_bm | card_bitmap(&cards)) == 0)
+                .count() as u64;
+            assert_eq!(blockers.live(&cards), brute);
+        }
+
+        let trials = 60_000;
+        let iso = iso_strata(&hand, &board, &[], trials, &rank_pool);
+        assert_eq!(iso.last().unwrap().end, trials);
+        let plain = [Stratum { cards: Vec::new(), end: trials, weight: 1.0 }];
+        let remaining = unseen;
+        let lookup = LookupEvaluator { table };
+        let equity = |strata: &[Stratum], seed: u64| {
+            let t = equity_trials(&EquitySetup {
+                hand,
+                board_cards: &board,
+                excluded_bm,
+                remaining: &remaining,
+                strata,
+                rank_pool: &rank_pool,
+                board_to_fill: 2,
+                seed,
+                evaluator: &lookup,
+                threads: 1,
+                legacy_rng: false,
+            });
+            (t.weighted.0 + 0.5 * t.weighted.1) / t.weighted.2
+        };
+        let (iso_eq, plain_eq) = (equity(&iso, 1), equity(&plain, 2));
+        // About four standard errors of the difference.
+        assert!((iso_eq - plain_eq).abs() < 0.012, "iso {} vs plain {}", iso_eq, plain_eq);
+    }
+
+    #[test]
+    fn legacy_equity_reproduces_old_engine() {
+        // `equity --hand AcAdKhQh5s --trials 20000 --seed 7` from the engine
+        // before xoshiro256++, on one CPU.
+        let table = eval_table();
+        let hand = parse_hand("AcAdKhQh5s").unwrap();
+        let excluded_bm = card_bitmap(&hand);
+        let remaining: Vec<u8> = (0..52u8).filter(|&c| excluded_bm & (1u64 << c) == 0).collect();
+        let strata = [Stratum { cards: Vec::new(), end: 20000, weight: 1.0 }];
+        let lookup = LookupEvaluator { table };
+        let t = equity_trials(&EquitySetup {
+            hand,
+            board_cards: &[],
+            excluded_bm,
+            remaining: &remaining,
+            strata: &strata,
+            rank_pool: &[],
+            board_to_fill: 5,
+            seed: 7,
+            evaluator: &lookup,
+            threads: 1,
+            legacy_rng: true,
+        });
+        assert_eq!((t.wins, t.ties, t.total), (11693, 261, 20000));
+    }
+
+    #[test]
+    fn exact_villain_outcomes_match_enumeration() {
+        let table = eval_table();
+        let hand = parse_hand("AcAdKhQh5s").unwrap();
+        let mut board = parse_cards_vec("Jh8h3c2d9h");
+        board.sort();
+        let board_3s = three_card_subsets_from_slice(&board);
+        let hero_rank = eval_best(&two_card_subsets(&hand), &board_3s, table);
+        let pool = parse_cards_vec("AhKcKsQsJsTh9c7h6h5d4d3s");
+
+        let (mut wins, mut total) = (0.0f64, 0u64);
+        let mut idx = [0usize, 1, 2, 3, 4];
+        loop {
+            let mut villain = idx.map(|i| pool[i]);
+            villain.sort();
+            let villain_rank = eval_best(&two_card_subsets(&villain), &board_3s, table);
+            if hero_rank < villain_rank { wins += 1.0; }
+            else if hero_rank == villain_rank { wins += 0.5; }
+            total += 1;
+            let Some(i) = (0..5).rev().find(|&i| idx[i] < pool.len() - 5 + i) else { break };
+            idx[i] += 1;
+            for j in (i + 1)..5 { idx[j] = idx[j - 1] + 1; }
+        }
+        let prepared = PreparedBoard::new(&board[..].try_into().unwrap(), table);
+        assert_eq!(exact_villain_outcomes(hero_rank, &pool, &prepared), (wins, total));
+    }
+
+    #[test]
+    fn drawing_dead_matches_exact_outcomes() {
+        let table = eval_table();
+        let mut rng = Xorshift64::new(mix_seed(31));
+        let deck: Vec<u8> = (0..52).collect();
+        let mut dead_seen = 0;
+        for _ in 0..300 {
+            let mut board = sample_villain(&deck, &mut rng);
+            board.sort();
+            let rest: Vec<u8> = deck.iter().copied().filter(|c| !board.contains(c)).collect();
+            let hand = sample_villain(&rest, &mut rng);
+            let pool: Vec<u8> = rest.iter().copied().filter(|c| !hand.contains(c)).take(14).collect();
+            let prepared = PreparedBoard::new(&board, table);
+            let hero_rank = prepared.best_rank(&hand);
+            let (wins, _) = exact_villain_outcomes(hero_rank, &pool, &prepared);
+            assert_eq!(drawing_dead(hero_rank, &pool, &prepared), wins == 0.0);
+            if wins == 0.0 { dead_seen += 1; }
+        }
+        assert!(dead_seen > 0);
+    }
+
+    #[test]
+    fn prepared_board_matches_eval_best() {
+        let table = eval_table();
+        let mut rng = Xorshift64::new(mix_seed(11));
+        let deck: Vec<u8> = (0..52).collect();
+        for _ in 0..50 {
+            let mut board = sample_villain(&deck, &mut rng);
+            board.sort();
+            let prepared = PreparedBoard::new(&board, table);
+            let board_3s = three_card_subsets(&board);
+            let pool: Vec<u8> = deck.iter().copied().filter(|c| !board.contains(c)).collect();
+            for _ in 0..200 {
+                let hand = sample_villain(&pool, &mut rng);
+                assert_eq!(prepared.best_rank(&hand), eval_best(&two_card_subsets(&hand), &board_3s, table));
+            }
+        }
+    }
+}
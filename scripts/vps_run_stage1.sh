@@ -105,7 +105,7 @@ set +e
 "$BINARY" accuracy \
   --bin "$OUTPUT" \
   --trials 2000000 \
-  --test-hands "JsTh5dTc4c,AcAhTh8d7c" | tee "$ACCURACY_LOG"
+  --test-hands "JsTh5dTc4c:AcAhTh8d7c" | tee "$ACCURACY_LOG"
 STATUS=${PIPESTATUS[0]}
 set -e
 
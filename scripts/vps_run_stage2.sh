@@ -73,7 +73,7 @@ echo ""
 $BINARY accuracy \
     --bin "$OUTPUT" \
     --trials 2000000 \
-    --test-hands "JsTh5dTc4c,AcAhTh8d7c"
+    --test-hands "JsTh5dTc4c:AcAhTh8d7c"
 
 echo ""
 echo "╔══════════════════════════════════════════════╗"